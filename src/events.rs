@@ -0,0 +1,134 @@
+//! `--message-format json`: one JSON object per line on stdout for each event, alongside the usual human output on
+//! stderr.  Every object has a `"reason"` field, like cargo's own JSON messages, which are forwarded as
+//! `{"reason":"cargo-message","crate":"...","message":{...}}`.
+
+use super::*;
+
+use std::io::Write as _;
+
+
+
+/// A single JSON event, built up field by field, then written as one line by [Event::emit].
+pub(super) struct Event(String);
+
+impl Event {
+    pub(super) fn new(reason: &str) -> Self {
+        let mut e = Self(String::from("{"));
+        e.key("reason");
+        e.0.push_str(&quote(reason));
+        e
+    }
+
+    pub(super) fn str(mut self, key: &str, value: impl Display) -> Self {
+        self.key(key);
+        self.0.push_str(&quote(&value.to_string()));
+        self
+    }
+
+    pub(super) fn path(self, key: &str, value: &Path) -> Self { self.str(key, value.display()) }
+
+    pub(super) fn opt_path(mut self, key: &str, value: Option<&Path>) -> Self {
+        match value {
+            Some(value) => self.path(key, value),
+            None        => { self.key(key); self.0.push_str("null"); self },
+        }
+    }
+
+    pub(super) fn strs<S: Display>(mut self, key: &str, values: impl IntoIterator<Item = S>) -> Self {
+        self.key(key);
+        self.0.push('[');
+        for (i, value) in values.into_iter().enumerate() {
+            if i != 0 { self.0.push(','); }
+            self.0.push_str(&quote(&value.to_string()));
+        }
+        self.0.push(']');
+        self
+    }
+
+    pub(super) fn num(mut self, key: &str, value: impl Into<f64>) -> Self {
+        self.key(key);
+        let value = value.into();
+        if value.is_finite() { write!(&mut self.0, "{}", value).unwrap(); } else { self.0.push_str("null"); }
+        self
+    }
+
+    pub(super) fn bool(mut self, key: &str, value: bool) -> Self {
+        self.key(key);
+        self.0.push_str(if value { "true" } else { "false" });
+        self
+    }
+
+    /// Embed `json` (e.g. one of cargo's own messages) verbatim.
+    pub(super) fn raw(mut self, key: &str, json: &str) -> Self {
+        self.key(key);
+        self.0.push_str(json);
+        self
+    }
+
+    /// Write the event to stdout, if `--message-format json` was requested (see [Term::json].)
+    pub(super) fn emit(mut self, term: &Term) {
+        if !term.json() { return }
+        self.0.push('}');
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        let _ = writeln!(stdout, "{}", self.0);
+        let _ = stdout.flush();
+    }
+
+    fn key(&mut self, key: &str) {
+        if self.0.len() > 1 { self.0.push(','); }
+        self.0.push_str(&quote(key));
+        self.0.push(':');
+    }
+}
+
+fn quote(s: &str) -> String {
+    let mut o = String::with_capacity(s.len() + 2);
+    o.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"'     => o.push_str("\\\""),
+            '\\'    => o.push_str("\\\\"),
+            '\n'    => o.push_str("\\n"),
+            '\r'    => o.push_str("\\r"),
+            '\t'    => o.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => write!(&mut o, "\\u{:04x}", ch as u32).unwrap(),
+            ch      => o.push(ch),
+        }
+    }
+    o.push('"');
+    o
+}
+
+/// Decode the JSON string starting with the `"` at the start of `s` (the inverse of [quote]), ignoring anything after it.
+pub(super) fn unquote(s: &str) -> Option<String> {
+    fn hex(chars: &mut std::str::Chars) -> Option<u32> { u32::from_str_radix(&chars.take(4).collect::<String>(), 16).ok() }
+
+    let mut chars = s.strip_prefix('"')?.chars();
+    let mut o = String::new();
+    loop {
+        match chars.next()? {
+            '"'     => return Some(o),
+            '\\'    => match chars.next()? {
+                '"'     => o.push('"'),
+                '\\'    => o.push('\\'),
+                '/'     => o.push('/'),
+                'n'     => o.push('\n'),
+                'r'     => o.push('\r'),
+                't'     => o.push('\t'),
+                'b'     => o.push('\u{8}'),
+                'f'     => o.push('\u{C}'),
+                'u'     => {
+                    let mut ch = hex(&mut chars)?;
+                    if (0xD800 .. 0xDC00).contains(&ch) { // surrogate pair
+                        if chars.next()? != '\\' || chars.next()? != 'u' { return None }
+                        ch = 0x10000 + ((ch - 0xD800) << 10) + hex(&mut chars)?.checked_sub(0xDC00)?;
+                    }
+                    o.push(char::from_u32(ch).unwrap_or(char::REPLACEMENT_CHARACTER));
+                },
+                _other  => return None,
+            },
+            ch      => o.push(ch),
+        }
+    }
+}
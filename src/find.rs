@@ -0,0 +1,101 @@
+//! [ensure_tool] and [find_tool]: locate the tools a build script (or xtask) asks for in its own `Cargo.toml`'s
+//! `[*.metadata.local-install]`, instead of hardcoding `bin/...` paths.
+
+use super::*;
+
+
+
+/// Variables cargo sets for build scripts that `cargo install` (or the build scripts of the crates it builds) would
+/// otherwise pick up, changing the cache key at best, and misconfiguring the build at worst.
+const BUILD_SCRIPT_ENV : &[&str] = &[
+    "RUSTC",
+    "RUSTDOC",
+    "RUSTC_LINKER",
+    "CARGO_ENCODED_RUSTFLAGS",
+    "CARGO_CFG_*",
+    "CARGO_FEATURE_*",
+    "CARGO_MANIFEST_*",
+    "CARGO_PKG_*",
+];
+
+/// Install the tools in the caller's `Cargo.toml` metadata (if they aren't already), and return the absolute path of
+/// the bin named `name` (without any `.exe`.)
+///
+/// From a build script, the metadata is read from the `Cargo.toml` in `CARGO_MANIFEST_DIR`, and
+/// `cargo:rerun-if-changed` lines are printed for it and the bin.  Otherwise, the nearest `Cargo.toml` in or above the
+/// current directory is used.
+///
+/// ```no_run
+/// // build.rs
+/// fn main() {
+///     let wasm_bindgen = cargo_local_install::ensure_tool("wasm-bindgen").unwrap();
+///     let status = std::process::Command::new(wasm_bindgen).arg("--version").status().unwrap();
+///     assert!(status.success());
+/// }
+/// ```
+pub fn ensure_tool(name: &str) -> Result<PathBuf, Error> {
+    let (dir, build_script) = caller_manifest_dir()?;
+    let mut options = InstallOptions::new().manifest_dir(&dir).verbosity(Verbosity::Quiet);
+    if build_script { options.env_remove = BUILD_SCRIPT_ENV.iter().map(OsString::from).collect(); }
+    let report = Installer::new(options).install()?;
+
+    let links = report.tools().flat_map(|tool| tool.links.iter()).map(|(link, _)| link).collect::<Vec<_>>();
+    let bin = bin_names(name).iter().find_map(|bin| links.iter().find(|link| link.file_name() == Some(bin)));
+    let bin = bin.ok_or_else(|| error!(Manifest, None, "none of the crates in the local-install metadata of {} have a bin named `{}`", manifest_display(&dir, &report), name))?;
+    let bin = absolute(bin)?;
+
+    if build_script {
+        for manifest in report.sets.iter().filter_map(|set| set.manifest.as_ref()) { println!("cargo:rerun-if-changed={}", manifest.display()); }
+        println!("cargo:rerun-if-changed={}", bin.display());
+    }
+    Ok(bin)
+}
+
+/// Like [ensure_tool], but never installs anything: returns the absolute path of the bin named `name` if it's already
+/// been linked into the `bin` directory of the caller's `Cargo.toml`, or an error suggesting `cargo local-install`.
+pub fn find_tool(name: &str) -> Result<PathBuf, Error> {
+    let (dir, build_script) = caller_manifest_dir()?;
+    let sets = manifest::find_installs(&dir, None)?;
+    if build_script {
+        for manifest in sets.iter().filter_map(|set| set.src.as_ref()) { println!("cargo:rerun-if-changed={}", manifest.display()); }
+    }
+
+    let names = bin_names(name);
+    let bin = sets.iter().find_map(|set| names.iter().map(|bin| set.bin.join(bin)).find(|bin| bin.exists())); // not dangling
+    let bin = bin.ok_or_else(|| error!(Link, None, "`{}` isn't installed in {}, run `cargo local-install` first", name, match sets.first() {
+        Some(set)   => set.bin.display().to_string(),
+        None        => format!("{} (no local-install metadata found)", dir.display()),
+    }))?;
+    let bin = absolute(&bin)?;
+
+    if build_script { println!("cargo:rerun-if-changed={}", bin.display()); }
+    Ok(bin)
+}
+
+/// The directory to search for a `Cargo.toml` in, and whether we're in a build script.
+fn caller_manifest_dir() -> Result<(PathBuf, bool), Error> {
+    match (std::env::var_os("OUT_DIR"), std::env::var_os("CARGO_MANIFEST_DIR")) {
+        (Some(_), Some(dir))    => Ok((PathBuf::from(dir), true)),
+        _other                  => Ok((std::env::current_dir().map_err(|err| error!(Io, err, "unable to determine cwd: {}", err))?, false)),
+    }
+}
+
+/// The file names a bin named `name` might have been linked (or shimmed) as, most directly runnable first.
+fn bin_names(name: &str) -> Vec<OsString> {
+    let mut names = vec![OsString::from(format!("{}{}", name, std::env::consts::EXE_SUFFIX))];
+    if cfg!(windows) { names.push(format!("{}.cmd", name).into()); }
+    names.push(name.into());
+    names.dedup();
+    names
+}
+
+fn manifest_display(dir: &Path, report: &InstallReport) -> String {
+    match report.sets.iter().find_map(|set| set.manifest.as_ref()) {
+        Some(manifest)  => manifest.display().to_string(),
+        None            => dir.display().to_string(),
+    }
+}
+
+fn absolute(path: &Path) -> Result<PathBuf, Error> {
+    std::path::absolute(path).map_err(|err| error!(Io, err, "unable to determine absolute path of {}: {}", path.display(), err))
+}
@@ -0,0 +1,532 @@
+//! The library API: describe some [Tool]s with [InstallOptions], then [Installer::install] them.
+//!
+//! ```no_run
+//! # fn a() -> Result<(), cargo_local_install::Error> {
+//! use cargo_local_install::*;
+//!
+//! let options = InstallOptions::new()
+//!     .tool(Tool::new("cargo-web").version("^0.6"))
+//!     .tool(Tool::new("wasm-pack").git("https://github.com/rustwasm/wasm-pack").tag("v0.9.1"))
+//!     .bin_dir("bin")
+//!     .locked(true)
+//!     .verbosity(Verbosity::Quiet);
+//! Installer::new(options).install()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use super::*;
+
+
+
+/// A single crate to install, and where to get it from.  Defaults to the latest version on crates.io.
+#[derive(Debug, Clone)]
+pub struct Tool {
+    pub(super) name:        OsString,
+    pub(super) flags:       Vec<InstallFlag>,
+    pub(super) toolchain:   Option<String>,
+}
+
+impl Tool {
+    /// The crate named `name`.
+    pub fn new(name: impl Into<OsString>) -> Self { Self { name: name.into(), flags: Vec::new(), toolchain: None } }
+
+    /// A version requirement, such as `"^0.6"` or `"=1.2.3"` (`cargo install --version ...`)
+    pub fn version(self, version: impl Into<OsString>) -> Self { self.flag("--version", Some(version.into())) }
+
+    /// Install from a git repository (`cargo install --git ...`)
+    pub fn git(self, url: impl Into<OsString>) -> Self { self.flag("--git", Some(url.into())) }
+
+    /// With [Tool::git], the branch to use (`cargo install --branch ...`)
+    pub fn branch(self, branch: impl Into<OsString>) -> Self { self.flag("--branch", Some(branch.into())) }
+
+    /// With [Tool::git], the tag to use (`cargo install --tag ...`)
+    pub fn tag(self, tag: impl Into<OsString>) -> Self { self.flag("--tag", Some(tag.into())) }
+
+    /// With [Tool::git], the commit to use (`cargo install --rev ...`)
+    pub fn rev(self, rev: impl Into<OsString>) -> Self { self.flag("--rev", Some(rev.into())) }
+
+    /// Install from a local directory (`cargo install --path ...`)
+    pub fn path(self, path: impl Into<PathBuf>) -> Self { self.flag("--path", Some(path.into().into())) }
+
+    /// Install from an alternative registry (`cargo install --registry ...`)
+    pub fn registry(self, registry: impl Into<OsString>) -> Self { self.flag("--registry", Some(registry.into())) }
+
+    /// Install from an alternative registry index (`cargo install --index ...`)
+    pub fn index(self, index: impl Into<OsString>) -> Self { self.flag("--index", Some(index.into())) }
+
+    /// Features to enable (`cargo install --features ...`)
+    pub fn features<F: AsRef<str>>(self, features: impl IntoIterator<Item = F>) -> Self {
+        let features = features.into_iter().map(|f| f.as_ref().to_string()).collect::<Vec<_>>().join(",");
+        self.flag("--features", Some(features.into()))
+    }
+
+    /// Enable all features (`cargo install --all-features`)
+    pub fn all_features(self) -> Self { self.flag("--all-features", None) }
+
+    /// Don't enable the `default` feature (`cargo install --no-default-features`)
+    pub fn no_default_features(self) -> Self { self.flag("--no-default-features", None) }
+
+    /// The rustup toolchain to build with, such as `"nightly"` (`cargo +nightly install ...`, with the `cargo` on `PATH`
+    /// rather than `$CARGO`: only rustup's proxy understands `+toolchain`.)
+    pub fn toolchain(mut self, toolchain: impl Into<String>) -> Self { self.toolchain = Some(toolchain.into()); self }
+
+    fn flag(mut self, flag: &str, arg: Option<OsString>) -> Self {
+        self.flags.retain(|f| f.flag != flag);
+        self.flags.push(InstallFlag::new(flag, arg.into_iter().collect()));
+        self
+    }
+}
+
+
+
+/// How much [Installer::install] reports as it goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Verbosity {
+    /// Only warnings, errors, and the output of failed builds (`-q`)
+    Quiet,
+    /// Status lines and cargo's output
+    #[default]
+    Normal,
+    /// Also print the commands being run (`-v`)
+    Verbose,
+}
+
+
+
+/// Everything [Installer::install] needs to know.  Without any [InstallOptions::tool]s, the
+/// `[package.metadata.local-install]` and `[workspace.metadata.local-install]` tables of the nearest `Cargo.toml` in or
+/// above the current directory (or [InstallOptions::manifest_dir]) are installed instead.
+#[derive(Clone, Default)]
+pub struct InstallOptions {
+    pub(super) tools:           Vec<Tool>,
+    pub(super) flags:           Vec<InstallFlag>, // for every tool
+    pub(super) bin_dir:         Option<PathBuf>,
+    pub(super) target_dir:      Option<PathBuf>,
+    pub(super) cache_dir:       Option<PathBuf>,
+    pub(super) locked:          Option<bool>,
+    pub(super) link_mode:       Option<LinkMode>,
+    pub(super) toolchain:       Option<String>,
+    pub(super) dry_run:         bool,
+    pub(super) verbosity:       Verbosity,
+    pub(super) sink:            Option<term::Sink>,
+    pub(super) jobs:            Option<usize>,
+    pub(super) parallel:        usize,
+    pub(super) keep_going:      bool,
+    pub(super) key_env:         Vec<OsString>,
+    pub(super) manifest_dir:    Option<PathBuf>,
+    pub(super) json:            bool,
+    pub(super) env_remove:      Vec<OsString>, // e.g. build script variables that shouldn't leak into `cargo install`
+    pub(super) runner:          Option<Arc<dyn CargoRunner>>,
+}
+
+impl InstallOptions {
+    pub fn new() -> Self { Self::default() }
+
+    /// Install `tool` (in addition to any previously added tools.)
+    pub fn tool(mut self, tool: Tool) -> Self { self.tools.push(tool); self }
+
+    /// Install all of `tools`.
+    pub fn tools(mut self, tools: impl IntoIterator<Item = Tool>) -> Self { self.tools.extend(tools); self }
+
+    /// Where to link bins to (`--out-bin ...`), instead of `bin` (or the `bin` next to each `Cargo.toml`.)
+    pub fn bin_dir(mut self, dir: impl Into<PathBuf>) -> Self { self.bin_dir = Some(dir.into()); self }
+
+    /// Where to build (`--target-dir ...`), instead of `target` in the cache directory.
+    pub fn target_dir(mut self, dir: impl Into<PathBuf>) -> Self { self.target_dir = Some(dir.into()); self }
+
+    /// Where to cache builds (`--cache-dir ...`), instead of `$CARGO_HOME/local-install` or `~/.cargo/local-install`.
+    /// The `CARGO_LOCAL_INSTALL_HOME` environment variable takes precedence over this.
+    pub fn cache_dir(mut self, dir: impl Into<PathBuf>) -> Self { self.cache_dir = Some(dir.into()); self }
+
+    /// Use (`true`) or ignore (`false`) each crate's `Cargo.lock` (`--locked` / `--unlocked`.)
+    pub fn locked(mut self, locked: bool) -> Self { self.locked = Some(locked); self }
+
+    /// How to put bins into the bin directory (`--link-mode ...`.)
+    pub fn link_mode(mut self, mode: LinkMode) -> Self { self.link_mode = Some(mode); self }
+
+    /// Build every tool with this rustup toolchain (`+toolchain`), instead of each [Tool::toolchain] or `$CARGO`.
+    /// Like [Tool::toolchain], this runs the `cargo` on `PATH`, which must be rustup's proxy.
+    pub fn toolchain(mut self, toolchain: impl Into<String>) -> Self { self.toolchain = Some(toolchain.into()); self }
+
+    /// Only report what would be installed (`--dry-run`.)
+    pub fn dry_run(mut self, dry_run: bool) -> Self { self.dry_run = dry_run; self }
+
+    /// How much to report.
+    pub fn verbosity(mut self, verbosity: Verbosity) -> Self { self.verbosity = verbosity; self }
+
+    /// Send every line of human readable output (including cargo's) to `sink`, uncolored, instead of stderr.
+    pub fn log_to(mut self, sink: impl Fn(&str) + Send + Sync + 'static) -> Self { self.sink = Some(Arc::new(sink)); self }
+
+    /// Parallel jobs per build (`--jobs ...`.)
+    pub fn jobs(mut self, jobs: usize) -> Self { self.jobs = Some(jobs); self }
+
+    /// Crates to build at once (`--parallel ...`.)
+    pub fn parallel(mut self, parallel: usize) -> Self { self.parallel = parallel; self }
+
+    /// Keep installing other tools after one fails (`--keep-going`.)
+    pub fn keep_going(mut self, keep_going: bool) -> Self { self.keep_going = keep_going; self }
+
+    /// Reinstall even if already cached (`--force`.)
+    pub fn force(mut self, force: bool) -> Self {
+        self.flags.retain(|f| f.flag != "--force");
+        if force { self.flags.push(InstallFlag::new("--force", Vec::new())); }
+        self
+    }
+
+    /// Also cache separately by the environment variable `var` (`--key-env ...`.)
+    pub fn key_env(mut self, var: impl Into<OsString>) -> Self { self.key_env.push(var.into()); self }
+
+    /// Also print `--message-format json` events to stdout, one JSON object per line.
+    pub fn message_format_json(mut self, json: bool) -> Self { self.json = json; self }
+
+    /// Run cargo with `runner` instead of spawning processes (for tests.)
+    #[doc(hidden)]
+    #[cfg(feature = "test-runner")]
+    pub fn runner(mut self, runner: impl CargoRunner + 'static) -> Self { self.runner = Some(Arc::new(runner)); self }
+
+    /// Where to look for a `Cargo.toml` with metadata, if no [InstallOptions::tool]s were added, instead of the current directory.
+    pub fn manifest_dir(mut self, dir: impl Into<PathBuf>) -> Self { self.manifest_dir = Some(dir.into()); self }
+}
+
+
+
+/// Runs `cargo install` for each tool in [InstallOptions], and links the results into bin directories.
+pub struct Installer {
+    options: InstallOptions,
+}
+
+impl Installer {
+    /// An installer for `options`, which does nothing until [Installer::install]ed.
+    pub fn new(options: InstallOptions) -> Self { Self { options } }
+
+    /// Install everything, returning what was installed where, or the first error (or, with
+    /// [InstallOptions::keep_going], a summary of the errors.)
+    pub fn install(&self) -> Result<InstallReport, Error> { self.run(&CliOptions::default()) }
+
+    /// [Installer::install], or `--verify` or `--show-log` instead, per the command line's `cli` options.
+    /// Failures are also reported as an `error` event, so library callers asking for JSON get one too.
+    pub(super) fn run(&self, cli: &CliOptions) -> Result<InstallReport, Error> {
+        let result = self.run_unreported(cli);
+        if let Err(err) = result.as_ref() { Event::new("error").str("message", err).emit(&Term::new(None, false, self.options.json)); }
+        result
+    }
+
+    fn run_unreported(&self, cli: &CliOptions) -> Result<InstallReport, Error> {
+        let color = term::color_choice(cli.color.as_deref()).map_err(|err| error!(Args, None, "--color: {}", err))?;
+        let term = &Term::new(self.options.sink.clone(), color, self.options.json);
+
+        let InstallOptions {
+            tools, flags: mut options, bin_dir: maybe_dst_bin, target_dir, cache_dir, locked, link_mode, toolchain, dry_run, verbosity, sink: _,
+            jobs, parallel, keep_going, key_env: extra_key_env, manifest_dir, env_remove, runner, json: _,
+        } = self.options.clone();
+        let CliOptions { path_warning, color: _, verify, verify_all, rebuild, ref show_log } = *cli;
+        let parallel = parallel.max(1);
+
+        let start = std::time::Instant::now();
+
+        let runner = runner.unwrap_or_else(|| Arc::new(ProcessRunner));
+        let quiet   = verbosity == Verbosity::Quiet;
+        let verbose = verbosity == Verbosity::Verbose;
+        if verbose { options.push(InstallFlag::new("--verbose", Vec::new())); } // not `--quiet`: logs get everything, and the terminal only what `quiet` allows
+        let mut key_env = DEFAULT_KEY_ENV.iter().map(OsString::from).collect::<Vec<_>>();
+        key_env.extend(extra_key_env);
+
+        let (global_dir, from) = cache_root(cache_dir)?;
+        let crates_cache_dir = global_dir.join("crates");
+
+        if let Some(krate) = show_log {
+            let krate = krate.to_string_lossy();
+            let pointer = last_log_pointer(&crates_cache_dir, &krate);
+            let log = std::fs::read_to_string(&pointer).map_err(|err| error!(Io, err, "--show-log: no build of `{}` recorded in {}: {}", krate, pointer.display(), err))?;
+            let log = Path::new(log.trim_end_matches(&['\r', '\n'][..]));
+            let text = std::fs::read_to_string(log).map_err(|err| error!(Io, err, "--show-log: unable to read {}: {}", log.display(), err))?;
+            Event::new("build-log").str("crate", &krate).path("log", log).str("text", &text).emit(term);
+            for line in text.lines() { term.line(line); }
+            return Ok(InstallReport::default());
+        }
+
+        let locked = locked.unwrap_or_else(|| {
+            if !tools.is_empty() { warnln!(term, "either specify --locked to use the same dependencies the crate was built with, or --unlocked to get rid of this warning"); }
+            false
+        });
+        if locked {
+            options.push(InstallFlag::new("--locked", Vec::new()));
+        }
+
+        let mut installs = if tools.is_empty() {
+            let dir = match manifest_dir { Some(dir) => dir, None => std::env::current_dir().map_err(|err| error!(Io, err, "unable to determine cwd: {}", err))? };
+            manifest::find_installs(&dir, maybe_dst_bin.clone()).map_err(|err| error!(Manifest, err, "error enumerating Cargo.tomls: {}", err))?
+        } else {
+            vec![InstallSet {
+                bin:        maybe_dst_bin.clone().unwrap_or_else(|| PathBuf::from("bin")),
+                src:        None,
+                installs:   tools.into_iter().map(|t| Install { name: t.name, flags: t.flags, link_mode: None, toolchain: t.toolchain, shim: Shim::default() }).collect(),
+            }]
+        };
+
+        if installs.is_empty() && !(verify && verify_all) {
+            return Err(error!(Args, None, "no crates specified"))
+        }
+        if (verify_all || rebuild) && !verify {
+            return Err(error!(Args, None, "--all and --rebuild require --verify"))
+        }
+
+        if !dry_run { check_writable(&global_dir)?; }
+        if verbose { statusln!(term, "Cache", "{} (from {})", global_dir.display(), from); }
+
+        let target_dir = target_dir.map_or_else(|| Ok(global_dir.join("target")), canonicalize)?;
+        options.sort();
+
+        for set in installs.iter_mut() {
+            for install in set.installs.iter_mut() {
+                if toolchain.is_some() { install.toolchain = toolchain.clone(); }
+                install.flags.extend(options.clone());
+                for flag in install.flags.iter_mut().filter(|flag| flag.flag == "--path") {
+                    for arg in flag.args.iter_mut() { *arg = canonicalize(PathBuf::from(&*arg))?.into(); }
+                }
+                install.flags.sort();
+                install.flags.dedup(); // e.g. `--locked` from both metadata and the command line
+            }
+        }
+
+        // Every toolchain used (`None` being `$CARGO`) gets its own build environment and cargo version probe
+        let mut toolchains = BTreeMap::<Option<String>, (String, bool)>::new();
+        for install in installs.iter().flat_map(|set| set.installs.iter()) {
+            if toolchains.contains_key(&install.toolchain) { continue }
+            let build_env = build_environment(&*runner, &key_env, &env_remove, install.toolchain.as_deref());
+            if verbose { statusln!(term, "Environment", "{}{}", install.toolchain.as_ref().map_or(String::new(), |t| format!("+{}: ", t)), build_env.trim_end().replace('\n', "; ")); }
+            let hack = z_no_index_update_hack(&*runner, install.toolchain.as_deref());
+            toolchains.insert(install.toolchain.clone(), (build_env, hack));
+        }
+        let build_env = |install: &Install| toolchains[&install.toolchain].0.as_str();
+
+        let context = Context { dry_run, quiet, verbose, term: term.clone() };
+
+        for set in installs.iter() {
+            Event::new("set-discovered")
+                .path("bin", &set.bin)
+                .opt_path("manifest", set.src.as_deref())
+                .strs("crates", set.installs.iter().map(|i| i.name.to_string_lossy()))
+                .emit(term);
+        }
+
+        let mut relink_all = false;
+        if verify {
+            let mut entries = installs.iter().flat_map(|set| set.installs.iter()).map(|install| install.cache_dir(&crates_cache_dir, build_env(install))).collect::<Vec<_>>();
+            if verify_all {
+                entries = crates_cache_dir.read_dir().map(|dir| dir.filter_map(|e| e.ok()).filter(|e| e.file_type().is_ok_and(|ft| ft.is_dir())).map(|e| e.path()).collect()).unwrap_or_default();
+            }
+            entries.sort();
+            entries.dedup();
+            let bins = installs.iter().map(|set| set.bin.clone()).collect::<Vec<_>>();
+
+            let verified = verify::verify(&context, &entries, &bins);
+            if verified.problems == 0 {
+                return Ok(InstallReport::default());
+            } else if !rebuild {
+                return Err(error!(Verify, None, "{} problem(s) found, rerun with `--verify --rebuild` to repair", verified.problems));
+            }
+
+            for root in verified.bad_entries.iter() {
+                let _lock = FileLock::acquire(term, root.with_extension("lock"), root, quiet)?; // don't pull the entry out from under another process's build
+                std::fs::remove_dir_all(root).map_err(|err| error!(Cache, err, "unable to remove {}: {}", root.display(), err))?;
+                if !quiet { statusln!(term, "Removed", "`{}`", root.display()); }
+            }
+            relink_all = true;
+        }
+
+        let mut builds  = Vec::<Build>::new();              // one per distinct cache entry
+        let mut links   = Vec::<(Vec<InstallFlag>, Link)>::new(); // to link after building
+        let mut stamps  = Vec::<PathBuf>::new();            // `.built` files to touch after linking
+        let mut skipped = Vec::<(Vec<InstallFlag>, Link)>::new(); // up to date
+
+        for set in installs.into_iter() {
+            let any_local  = set.any_local();
+            let any_remote = set.any_remote();
+            if set.installs.is_empty() { continue }
+            assert!(any_local || any_remote);
+
+            let built = set.bin.join(".built");
+            let set_links = set.installs.iter().map(|install| Link {
+                name:       install.name.clone(),
+                root:       install.cache_dir(&crates_cache_dir, build_env(install)),
+                dst_bin:    set.bin.clone(),
+                mode:       link_mode.or(install.link_mode).unwrap_or(if install.shim.is_empty() { LinkMode::Auto } else { LinkMode::Shim }),
+                shim:       install.shim.clone(),
+                set:        set.src.clone(),
+            }).collect::<Vec<_>>();
+            for link in set_links.iter().filter(|link| link.mode != LinkMode::Shim && !link.shim.is_empty()) {
+                warnln!(term, "`{}` specifies `env` or `args`, which are ignored unless using --link-mode shim", link.name.to_string_lossy());
+            }
+            let records = LinkRecords::read(&set.bin);
+            let relink = set_links.iter().any(|link| !records.matches(link));
+            let dangling = set_links.iter().map(|link| link.is_dangling()).collect::<Vec<_>>();
+
+            let up_to_date = if !any_remote || relink || relink_all {
+                false
+            } else if let Some(src) = set.src.as_ref() {
+                let src_mod = src.metadata().ok().and_then(|m| m.modified().ok());
+                let built_mod = built.metadata().ok().and_then(|m| m.modified().ok());
+
+                let up_to_date = match (src_mod, built_mod) {
+                    (Some(src), Some(built))    => src < built,
+                    _other                      => false,
+                };
+
+                if up_to_date && !any_local && !dangling.contains(&true) {
+                    if verbose { statusln!(term, "Skipping", "`{}`: up to date", src.display()); }
+                    skipped.extend(set.installs.into_iter().map(|install| install.flags).zip(set_links));
+                    continue
+                }
+
+                up_to_date
+            } else {
+                false
+            };
+
+            for ((install, link), dangling) in set.installs.into_iter().zip(set_links).zip(dangling) {
+                if install.is_remote() && up_to_date {
+                    if !dangling { skipped.push((install.flags, link)); continue }
+                    if !quiet { statusln!(term, "Repairing", "`{}` in `{}`: missing or dangling links into `{}`", install.name.to_string_lossy(), set.bin.display(), link.root.display()); }
+                }
+                if !builds.iter().any(|b| b.root == link.root) { builds.push(Build { name: install.name, flags: install.flags.clone(), root: link.root.clone(), set: set.src.clone(), z_no_index_update_hack: toolchains[&install.toolchain].1, toolchain: install.toolchain }); }
+                links.push((install.flags, link));
+            }
+            if any_remote && set.src.is_some() { stamps.push(built); }
+        }
+
+        let single_build = builds.len() == 1;
+        let parallel = parallel.min(builds.len()).max(1);
+        let pool = BuildPool {
+            target_dirs: (0 .. parallel).map(|i| if i == 0 { target_dir.clone() } else {
+                let mut name = target_dir.file_name().unwrap_or_default().to_os_string();
+                name.push(format!("-{}", i));
+                target_dir.with_file_name(name)
+            }).collect(),
+            jobs: match (jobs, parallel) {
+                (Some(jobs), parallel)  => Some((jobs / parallel).max(1)),
+                (None, 1)               => None,
+                (None, parallel)        => std::thread::available_parallelism().ok().map(|n| (n.get() / parallel).max(1)),
+            },
+            keep_going,
+            env_remove,
+            runner,
+        };
+        let mut results = pool.run(&context, builds);
+        if !single_build {
+            for err in results.iter_mut().filter_map(|(_, _, result)| result.as_mut().err()) { err.one_of_many = true; }
+        }
+        // Without --keep-going, link whatever did get built before reporting the first failure
+        let first_error = if keep_going { None } else { results.iter().position(|(_, _, r)| r.is_err()).and_then(|pos| results.swap_remove(pos).2.err()) };
+
+        let mut tools = skipped.iter().map(|(flags, link)| ToolSummary::new(flags, link, None, Ok(Outcome::Skipped), link.expected_links())).collect::<Vec<_>>();
+        for (flags, link) in links.iter() {
+            let (duration, built) = match results.iter_mut().find(|(root, _, _)| *root == link.root) {
+                Some((_, duration, Ok(outcome)))    => (Some(*duration), Ok(*outcome)),
+                Some((_, duration, Err(err)))       => { // the first link of a build gets the original error (with its source), the rest copies
+                    let mut copy = err.duplicate();
+                    copy.set = link.set.clone();
+                    (Some(*duration), Err(std::mem::replace(err, copy)))
+                },
+                None                                => (None, Err(error!(Cache, None, "`{}` was never built", link.name.to_string_lossy()))),
+            };
+            if first_error.is_some() && built.is_err() { continue }
+            let (linked, bins) = match built {
+                Ok(_) if dry_run => (built, Vec::new()),
+                Ok(outcome) => match link::link_bins(&context, link) {
+                    Ok(bins)                => (Ok(outcome), bins),
+                    Err(err) if keep_going  => (Err(err.with_tool(&link.name).with_set(link.set.as_deref())), Vec::new()),
+                    Err(err)                => return Err(err.with_tool(&link.name).with_set(link.set.as_deref())),
+                },
+                Err(err) => (Err(err), Vec::new()),
+            };
+            tools.push(ToolSummary::new(flags, link, duration, linked, bins));
+        }
+        tools.sort_by(|a, b| (&a.bin, &a.name).cmp(&(&b.bin, &b.name)));
+        if let Some(err) = first_error { return Err(err) }
+
+        if !dry_run {
+            for built in stamps.iter() {
+                let failed = tools.iter().any(|tool| tool.result.is_err() && Some(tool.bin.as_path()) == built.parent());
+                if failed { continue } // retry next time
+                std::fs::write(built, "").map_err(|err| error!(Link, err, "unable to create {}: {}", built.display(), err))?;
+            }
+        }
+
+        let stop = std::time::Instant::now();
+        if !quiet { statusln!(term, "Finished", "installing crate(s) in {:.2}s", (stop-start).as_secs_f32()); }
+        Event::new("finished").num("duration", (stop-start).as_secs_f64()).emit(term);
+        summary::report(term, &tools, quiet);
+        let failed = tools.iter().filter(|tool| tool.result.is_err()).count();
+        if path_warning {
+            if let Some(dst_bin) = maybe_dst_bin {
+                warnln!(term, "be sure to add `{}` to your PATH to be able to run the installed binaries", dst_bin.display());
+            } else {
+                warnln!(term, "be sure to add `$crate\\bin` path(s) to your PATH to be able to run the installed binaries");
+            }
+        }
+        if failed == 1 { return Err(Error { reported: true, ..tools.into_iter().find_map(|tool| tool.result.err()).unwrap() }) } // the summary already printed it
+        if failed > 0 { return Err(error!(Multiple, None, "{} of {} crate(s) failed to install", failed, tools.len())) }
+        Ok(InstallReport::new(tools))
+    }
+}
+
+/// Does `toolchain`'s cargo (see [cargo_command]) need `-Z no-index-update` to avoid redundant index updates?
+fn z_no_index_update_hack(runner: &dyn CargoRunner, toolchain: Option<&str>) -> bool {
+    let (maj, min, pat, stable) = runner::output(runner, cargo_command(toolchain).arg("--version").stderr(Stdio::null()).stdout(Stdio::piped())).map_or((0, 0, 0, false), |(stdout, _)|{
+        fn split_once<'a>(a: &'a str, sep: &str) -> Option<(&'a str, &'a str)> {
+            let i = a.find(sep);
+            i.map(|i| {
+                let (a,b) = a.split_at(i);
+                (a, &b[sep.len()..])
+            })
+        }
+
+        let o = &*stdout;
+        let ver = o.split(' ').nth(1).unwrap_or("");
+        let (maj, ver) = split_once(ver, ".").unwrap_or((ver, ""));
+        let (min, ver) = split_once(ver, ".").unwrap_or((ver, ""));
+        let (pat, pre) = split_once(ver, "-").unwrap_or((ver, ""));
+        let maj = maj.parse().unwrap_or(0u32);
+        let min = min.parse().unwrap_or(0u32);
+        let pat = pat.parse().unwrap_or(0u32);
+        let stable = pre.is_empty();
+        (maj, min, pat, stable)
+    });
+
+    // 1.26.0 flag: https://github.com/rust-lang/cargo/commit/b83ef97efb5d8d2a0d15f3dcd84f3f5d65a98193
+    // 1.26.0 var:  https://github.com/rust-lang/cargo/blob/41480f5cc50863600e05aa17d13264c88070436a/src/cargo/core/features.rs#L315
+    // 1.47.0 var:  https://github.com/rust-lang/cargo/blob/becb4c282b8f37469efb8f5beda45a5501f9d367/src/cargo/core/features.rs#L509
+    ((1, 26, 0, true) ..= (1, 47, 0, true)).contains(&(maj,min,pat,stable))
+}
+
+/// The cache directory, and where it came from: `CARGO_LOCAL_INSTALL_HOME`, then `cache_dir` (`--cache-dir`), then
+/// `$CARGO_HOME/local-install`, then `~/.cargo/local-install`.
+fn cache_root(cache_dir: Option<PathBuf>) -> Result<(PathBuf, &'static str), Error> {
+    let var = |name: &str| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let home = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    let (dir, from) = if let Some(dir) = var("CARGO_LOCAL_INSTALL_HOME") {
+        (dir, "CARGO_LOCAL_INSTALL_HOME")
+    } else if let Some(dir) = cache_dir {
+        (dir, "--cache-dir")
+    } else if let Some(cargo_home) = var("CARGO_HOME") {
+        (cargo_home.join("local-install"), "CARGO_HOME")
+    } else if let Some(home_dir) = var(home) {
+        (home_dir.join(".cargo").join("local-install"), home)
+    } else {
+        return Err(error!(Env, None, "couldn't determine cache dir: set CARGO_LOCAL_INSTALL_HOME, CARGO_HOME, or {}, or pass --cache-dir", home));
+    };
+    if dir.is_absolute() { return Ok((dir, from)) }
+    let cwd = std::env::current_dir().map_err(|err| error!(Io, err, "unable to determine cwd: {}", err))?;
+    Ok((cwd.join(dir), from)) // cache entries are linked to by absolute path
+}
+
+/// Create `dir` if necessary, and make sure files can be created in it, before spending time on builds.
+fn check_writable(dir: &Path) -> Result<(), Error> {
+    std::fs::create_dir_all(dir).map_err(|err| error!(Cache, err, "unable to create cache dir {}: {}", dir.display(), err))?;
+    let probe = dir.join(format!(".write-test-{}", std::process::id()));
+    std::fs::write(&probe, "").map_err(|err| error!(Cache, err, "cache dir {} isn't writable: {}", dir.display(), err))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
@@ -1,9 +1,20 @@
 #![forbid(unsafe_code)]
+#![allow(clippy::needless_doctest_main)] // examples are meant to be copied into `main.rs`
 
 #[macro_use] mod macros;
-#[cfg(    feature = "manifest") ] mod manifest;
-#[cfg(not(feature = "manifest"))] mod manifest { pub(super) fn find_cwd_installs(_maybe_dst_bin: Option<std::path::PathBuf>) -> Result<Vec<crate::InstallSet>, crate::Error> { Ok(Vec::new()) } }
-
+mod events;
+mod find;
+mod installer;
+mod link;
+mod lock;
+mod manifest;
+mod runner;
+mod sha256;
+mod summary;
+mod term;
+mod verify;
+
+use std::collections::BTreeMap;
 use std::env::ArgsOs;
 use std::fmt::{self, Display, Debug, Formatter, Write as _};
 use std::ffi::{OsStr, OsString};
@@ -11,27 +22,140 @@ use std::hash::*;
 use std::io::{self, BufRead, BufReader};
 use std::path::*;
 use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use events::Event;
+use link::{Link, LinkRecords};
+use runner::ProcessRunner;
+use lock::FileLock;
+use summary::ToolSummary;
+use term::Term;
+use verify::Checksums;
+
+pub use find::{ensure_tool, find_tool};
+pub use installer::{InstallOptions, Installer, Tool, Verbosity};
+pub use link::LinkMode;
+pub use manifest::{Manifest, MetadataTable, ToolRequirement, ToolSource};
+#[cfg(feature = "test-runner")] #[doc(hidden)] pub use runner::{CargoProcess, CargoRunner};
+#[cfg(not(feature = "test-runner"))] use runner::CargoRunner;
+pub use summary::{InstallReport, InstallSetReport, Outcome, ToolReport};
+
+
+
+/// A `cargo-local-install` error.  [Display] gives a human readable message, [Error::kind] says what went wrong, and
+/// [std::error::Error::source] gives the underlying I/O, TOML, etc. error, if any.
+pub struct Error {
+    kind:       ErrorKind,
+    message:    String,
+    source:     Option<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    tool:       Option<String>,
+    set:        Option<PathBuf>,
+    /// From one of several builds, so cargo's exit code isn't passed through to the process (see [Error::exit_code].)
+    one_of_many: bool,
+    /// Already printed (by the `--keep-going` summary), so the CLI shouldn't print it again.
+    reported:   bool,
+}
 
+impl Error {
+    fn new(kind: ErrorKind, message: String, source: Option<Box<dyn std::error::Error + Send + Sync + 'static>>) -> Self { Self { kind, message, source, tool: None, set: None, one_of_many: false, reported: false } }
 
+    /// What went wrong.
+    pub fn kind(&self) -> ErrorKind { self.kind }
 
-/// An opaque `cargo-local-install` error, currently meant for [Display] only.
-pub struct Error(String, Option<Inner>);
-impl Display for Error { fn fmt(&self, fmt: &mut Formatter) -> fmt::Result { write!(fmt, "{}", self.0) } }
-impl Debug   for Error { fn fmt(&self, fmt: &mut Formatter) -> fmt::Result { write!(fmt, "Error({:?})", self.0) } }
-impl std::error::Error for Error {}
+    /// The crate being installed when this error occured, if any.
+    pub fn tool(&self) -> Option<&str> { self.tool.as_deref() }
 
-enum Inner { Io(io::Error) }
-impl From<io::Error> for Inner { fn from(err: io::Error) -> Self { Inner::Io(err) } }
+    /// The `Cargo.toml` whose `[package.metadata.local-install]` or `[workspace.metadata.local-install]` was being
+    /// installed when this error occured, if any.
+    pub fn install_set(&self) -> Option<&Path> { self.set.as_deref() }
 
+    fn with_tool(mut self, tool: &OsStr) -> Self { self.tool.get_or_insert_with(|| tool.to_string_lossy().into_owned()); self }
+    fn with_set(mut self, set: Option<&Path>) -> Self { if self.set.is_none() { self.set = set.map(Path::to_path_buf) } self }
 
+    /// The process exit code `cargo local-install` uses for this error (see [ErrorKind::exit_code]).  Cargo's own exit
+    /// code is only passed through when a single crate was built, since it'd be ambiguous otherwise.
+    pub fn exit_code(&self) -> i32 {
+        match self.kind {
+            ErrorKind::BuildFailed { exit_code: Some(_) } if self.one_of_many => ErrorKind::BuildFailed { exit_code: None }.exit_code(),
+            kind => kind.exit_code(),
+        }
+    }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
-enum LogMode {
-    Quiet,
-    Normal,
-    Verbose,
+    /// Copy everything but the [std::error::Error::source].
+    fn duplicate(&self) -> Self { Self { kind: self.kind, message: self.message.clone(), source: None, tool: self.tool.clone(), set: self.set.clone(), one_of_many: self.one_of_many, reported: self.reported } }
 }
 
+impl Display for Error { fn fmt(&self, fmt: &mut Formatter) -> fmt::Result { write!(fmt, "{}", self.message) } }
+impl Debug   for Error { fn fmt(&self, fmt: &mut Formatter) -> fmt::Result { write!(fmt, "Error({:?}, {:?})", self.kind, self.message) } }
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> { self.source.as_ref().map(|err| &**err as &(dyn std::error::Error + 'static)) }
+}
+
+/// What kind of [Error] occured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Invalid or unsupported command line arguments.
+    Args,
+    /// A `Cargo.toml` couldn't be found, read, or parsed.
+    Manifest,
+    /// The environment is unusable (e.g. neither `CARGO_HOME` nor `HOME` is set.)
+    Env,
+    /// `cargo` couldn't be launched.
+    Spawn,
+    /// `cargo install` failed, with its exit code if it had one.
+    BuildFailed { exit_code: Option<i32> },
+    /// Bins couldn't be linked (or copied, etc.) into a `bin` directory.
+    Link,
+    /// The cache directory (e.g. `~/.cargo/local-install`) couldn't be read, written, or locked.
+    Cache,
+    /// `--verify` found problems.
+    Verify,
+    /// More than one crate failed to install (`--keep-going`.)
+    Multiple,
+    /// Some other I/O error.
+    Io,
+}
+
+impl ErrorKind {
+    /// The stable process exit code for this kind of error:
+    ///
+    /// | Exit code | Kind                                                                   |
+    /// | --------- | ---------------------------------------------------------------------- |
+    /// | 1         | [ErrorKind::Io]                                                        |
+    /// | 2         | [ErrorKind::Args]                                                      |
+    /// | 3         | [ErrorKind::Manifest]                                                  |
+    /// | 4         | [ErrorKind::Env]                                                       |
+    /// | 5         | [ErrorKind::Spawn]                                                     |
+    /// | 6         | [ErrorKind::Link]                                                      |
+    /// | 7         | [ErrorKind::Cache]                                                     |
+    /// | 8         | [ErrorKind::Verify]                                                    |
+    /// | 9         | [ErrorKind::Multiple]                                                  |
+    /// | 10        | [ErrorKind::BuildFailed] without an exit code                          |
+    /// | *cargo's* | [ErrorKind::BuildFailed] with an exit code (typically 101)             |
+    ///
+    /// [Error::exit_code] only passes cargo's exit code through if a single crate was built.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Io                                   => 1,
+            ErrorKind::Args                                 => 2,
+            ErrorKind::Manifest                             => 3,
+            ErrorKind::Env                                  => 4,
+            ErrorKind::Spawn                                => 5,
+            ErrorKind::Link                                 => 6,
+            ErrorKind::Cache                                => 7,
+            ErrorKind::Verify                               => 8,
+            ErrorKind::Multiple                             => 9,
+            ErrorKind::BuildFailed { exit_code: None }      => 10,
+            ErrorKind::BuildFailed { exit_code: Some(0) }   => 10, // never report success
+            ErrorKind::BuildFailed { exit_code: Some(n) }   => n,
+        }
+    }
+}
+
+
+
 #[derive(Debug)]
 struct InstallSet {
     bin:        PathBuf,
@@ -46,8 +170,22 @@ impl InstallSet {
 
 #[derive(Debug)]
 struct Install {
-    name:   OsString,
-    flags:  Vec<InstallFlag>,
+    name:       OsString,
+    flags:      Vec<InstallFlag>,
+    link_mode:  Option<LinkMode>,
+    toolchain:  Option<String>,
+    shim:       Shim,
+}
+
+/// Extra environment variables and leading arguments for `--link-mode shim` launchers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Shim {
+    env:    BTreeMap<String, String>,
+    args:   Vec<String>,
+}
+
+impl Shim {
+    fn is_empty(&self) -> bool { self.env.is_empty() && self.args.is_empty() }
 }
 
 impl Install {
@@ -62,6 +200,17 @@ struct InstallFlag {
 }
 impl InstallFlag {
     fn new(flag: impl Into<OsString>, args: Vec<OsString>) -> Self { Self { flag: flag.into(), args } }
+
+    /// Returns `false` for presentation-only flags which change how `cargo install` reports progress or where it keeps
+    /// intermediate artifacts, but not which binaries it ultimately produces.  Only build-affecting flags are hashed.
+    fn affects_build(&self) -> bool {
+        match &*self.flag.to_string_lossy() {
+            "-q" | "--quiet" | "-v" | "--verbose" | "--color" |
+            "-j" | "--jobs" | "-f" | "--force" | "--target-dir"
+            => false,
+            _other => true,
+        }
+    }
 }
 
 /// Run an install after reading the executable name / subcommand.
@@ -77,7 +226,8 @@ impl InstallFlag {
 /// }
 /// ```
 pub fn exec_from_args_os_after_exe(args: ArgsOs) -> ! {
-    run_from_args_os_after_exe(args).unwrap_or_else(|err| fatal!("{}", err));
+    let (term, result) = run_impl(InstallOptions::default(), args);
+    result.unwrap_or_else(|err| if err.reported { std::process::exit(err.exit_code()) } else { fatal!(&term, err.exit_code(), "{}", err) });
     std::process::exit(0);
 }
 
@@ -92,7 +242,7 @@ pub fn exec_from_args_os_after_exe(args: ArgsOs) -> ! {
 ///     cargo_local_install::run_from_args_os_after_exe(args).unwrap();
 /// }
 /// ```
-pub fn run_from_args_os_after_exe(args: ArgsOs) -> Result<(), Error> {
+pub fn run_from_args_os_after_exe(args: ArgsOs) -> Result<InstallReport, Error> {
     run_from_strs(args)
 }
 
@@ -127,236 +277,271 @@ pub fn run_from_args_os_after_exe(args: ArgsOs) -> Result<(), Error> {
 /// # Ok(())
 /// # }
 /// ```
-pub fn run_from_strs<Args: Iterator<Item = Arg>, Arg: Into<OsString> + AsRef<OsStr>>(args: Args) -> Result<(), Error> {
-    let start = std::time::Instant::now();
-
-    let (maj, min, pat, stable) = Command::new("cargo").arg("--version").stderr(std::process::Stdio::null()).stdout(std::process::Stdio::piped()).output().map_or((0, 0, 0, false), |o|{
-        fn split_once<'a>(a: &'a str, sep: &str) -> Option<(&'a str, &'a str)> {
-            let i = a.find(sep);
-            i.map(|i| {
-                let (a,b) = a.split_at(i);
-                (a, &b[sep.len()..])
-            })
-        }
+pub fn run_from_strs<Args: Iterator<Item = Arg>, Arg: Into<OsString> + AsRef<OsStr>>(args: Args) -> Result<InstallReport, Error> {
+    run_impl(InstallOptions::default(), args).1
+}
 
-        let o = &*String::from_utf8_lossy(&o.stdout);
-        let ver = o.split(' ').nth(1).unwrap_or("");
-        let (maj, ver) = split_once(ver, ".").unwrap_or((ver, ""));
-        let (min, ver) = split_once(ver, ".").unwrap_or((ver, ""));
-        let (pat, pre) = split_once(ver, "-").unwrap_or((ver, ""));
-        let maj = maj.parse().unwrap_or(0u32);
-        let min = min.parse().unwrap_or(0u32);
-        let pat = pat.parse().unwrap_or(0u32);
-        let stable = pre.is_empty();
-        (maj, min, pat, stable)
-    });
-
-    // 1.26.0 flag: https://github.com/rust-lang/cargo/commit/b83ef97efb5d8d2a0d15f3dcd84f3f5d65a98193
-    // 1.26.0 var:  https://github.com/rust-lang/cargo/blob/41480f5cc50863600e05aa17d13264c88070436a/src/cargo/core/features.rs#L315
-    // 1.47.0 var:  https://github.com/rust-lang/cargo/blob/becb4c282b8f37469efb8f5beda45a5501f9d367/src/cargo/core/features.rs#L509
-    let z_no_index_update_hack = ((1, 26, 0, true) ..= (1, 47, 0, true)).contains(&(maj,min,pat,stable));
+/// [run_from_strs], but starting from `options` (e.g. with a fake `runner`) instead of the defaults.  For `tests/`.
+#[cfg(feature = "test-runner")]
+#[doc(hidden)]
+pub fn run_from_strs_with<Args: Iterator<Item = Arg>, Arg: Into<OsString> + AsRef<OsStr>>(options: InstallOptions, args: Args) -> Result<InstallReport, Error> {
+    run_impl(options, args).1
+}
 
+/// Parse and run, returning the result along with how to report errors (per `--color` and `--message-format`.)
+fn run_impl<Args: Iterator<Item = Arg>, Arg: Into<OsString> + AsRef<OsStr>>(mut options: InstallOptions, args: Args) -> (Term, Result<InstallReport, Error>) {
+    let mut cli = CliOptions { path_warning: true, ..CliOptions::default() };
+    let parsed = parse_args(args, &mut options, &mut cli);
+    let color = term::color_choice(cli.color.as_deref()).or_else(|_| term::color_choice(None)).unwrap_or(false);
+    let term = Term::new(None, color, options.json);
+    let result = match parsed {
+        Ok(true)    => help().map(|()| InstallReport::default()),
+        Ok(false)   => return (term, Installer::new(options).run(&cli)), // reports its own `error` event
+        Err(err)    => Err(err),
+    };
+    if let Err(err) = result.as_ref() { Event::new("error").str("message", err).emit(&term); }
+    (term, result)
+}
+
+/// Settings only the command line has, kept out of [InstallOptions].
+#[derive(Default)]
+struct CliOptions {
+    path_warning:   bool,
+    color:          Option<String>,
+    verify:         bool,
+    verify_all:     bool,
+    rebuild:        bool,
+    show_log:       Option<OsString>,
+}
+
+/// Parse command line arguments into `options` and `cli`, returning `true` if `--help` was requested instead.
+fn parse_args<Args: Iterator<Item = Arg>, Arg: Into<OsString> + AsRef<OsStr>>(args: Args, options: &mut InstallOptions, cli: &mut CliOptions) -> Result<bool, Error> {
     // XXX: I'll likely relax either "Into<OsString>" or "AsRef<OsStr>", but I haven't decided which just yet.
     let mut args = args.peekable();
 
-    let mut dry_run     = false;
-    let mut path_warning= true;
-    let mut log_mode    = LogMode::Normal;
-    let mut locked      = None;
-    let mut maybe_dst_bin     = None;
-    let mut target_dir  = None;
-    let mut path        = None;
-
-    let mut options     = Vec::<InstallFlag>::new(); // will get reordered for improved caching
-    let mut crates      = Vec::<OsString>::new();
+    // `cargo local-install +nightly ...`, like `cargo +nightly install ...`
+    if let Some(toolchain) = args.peek().and_then(|arg| arg.as_ref().to_str()?.strip_prefix('+').map(String::from)) {
+        args.next();
+        if toolchain.is_empty() { return Err(error!(Args, None, "+toolchain must name a toolchain")) }
+        options.toolchain = Some(toolchain);
+    }
 
     while let Some(arg) = args.next() {
         let arg = arg.into();
         let lossy = arg.to_string_lossy();
         match &*lossy {
-            "--help"        => return help(),
+            "--help"        => return Ok(true),
             //"--version"     => return version(), // XXX: Conflicts with version selection flag
 
             // We want to warn if `--locked` wasn't passed, since you probably wanted it
-            "--locked"      => locked = Some(true ),
-            "--unlocked"    => locked = Some(false), // new to cargo-local-install
+            "--locked"      => options.locked = Some(true ),
+            "--unlocked"    => options.locked = Some(false), // new to cargo-local-install
 
             // Custom-handled flags
-            "--root"        => maybe_dst_bin      = Some(PathBuf::from(args.next().ok_or_else(|| error!(None, "--root must specify a directory"))?.into()).join("bin")),
-            "--out-bin"     => maybe_dst_bin      = Some(PathBuf::from(args.next().ok_or_else(|| error!(None, "--out-bin must specify a directory"))?.into())), // new to cargo-local-install
-            "--target-dir"  => target_dir   = Some(canonicalize(PathBuf::from(args.next().ok_or_else(|| error!(None, "--target-dir must specify a directory"))?.into()))?),
-            "--path"        => path         = Some(canonicalize(PathBuf::from(args.next().ok_or_else(|| error!(None, "--path must specify a directory"))?.into()))?),
-            "--list"        => return Err(error!(None, "not yet implemented: --list (should this list global cache or local bins?)")),
-            "--no-track"    => return Err(error!(None, "not yet implemented: --no-track (the entire point of this crate is tracking...)")),
-            "-Z"            => return Err(error!(None, "not yet implemented: -Z flags")),
-            "--frozen"      => return Err(error!(None, "not yet implemented: --frozen (last I checked this never worked in cargo install anyways?)")), // https://github.com/rust-lang/cargo/issues/7169#issuecomment-515195574
-            "--offline"     => return Err(error!(None, "not yet implemented: --offline")),
-            "--dry-run"     => dry_run = true, // new to cargo-local-install
-            "--no-path-warning" => path_warning = false, // new to cargo-local-install
+            "--root"        => options.bin_dir      = Some(PathBuf::from(args.next().ok_or_else(|| error!(Args, None, "--root must specify a directory"))?.into()).join("bin")),
+            "--out-bin"     => options.bin_dir      = Some(PathBuf::from(args.next().ok_or_else(|| error!(Args, None, "--out-bin must specify a directory"))?.into())), // new to cargo-local-install
+            "--target-dir"  => options.target_dir   = Some(PathBuf::from(args.next().ok_or_else(|| error!(Args, None, "--target-dir must specify a directory"))?.into())),
+            "--cache-dir"   => options.cache_dir    = Some(PathBuf::from(args.next().ok_or_else(|| error!(Args, None, "--cache-dir must specify a directory"))?.into())), // new to cargo-local-install
+            "--path"        => options.flags.push(InstallFlag::new("--path", vec![args.next().ok_or_else(|| error!(Args, None, "--path must specify a directory"))?.into()])),
+            "--list"        => return Err(error!(Args, None, "not yet implemented: --list (should this list global cache or local bins?)")),
+            "--no-track"    => return Err(error!(Args, None, "not yet implemented: --no-track (the entire point of this crate is tracking...)")),
+            "-Z"            => return Err(error!(Args, None, "not yet implemented: -Z flags")),
+            "--frozen"      => return Err(error!(Args, None, "not yet implemented: --frozen (last I checked this never worked in cargo install anyways?)")), // https://github.com/rust-lang/cargo/issues/7169#issuecomment-515195574
+            "--offline"     => return Err(error!(Args, None, "not yet implemented: --offline")),
+            "--dry-run"     => options.dry_run = true, // new to cargo-local-install
+            "--no-path-warning" => cli.path_warning = false, // new to cargo-local-install
+            "-j" | "--jobs" => options.jobs = Some(parse_count(&lossy, args.next())?),
+            "--parallel"    => options.parallel = parse_count(&lossy, args.next())?, // new to cargo-local-install
+            "--message-format" => match &*args.next().ok_or_else(|| error!(Args, None, "--message-format must specify a format"))?.into().to_string_lossy() {
+                "human"     => options.json = false,
+                "json"      => options.json = true,
+                other       => return Err(error!(Args, None, "--message-format: unknown format {:?}, expected human or json", other)),
+            }, // new to cargo-local-install
+            "--keep-going"  => options.keep_going = true, // new to cargo-local-install
+            "--verify"      => cli.verify = true, // new to cargo-local-install
+            "--all"         => cli.verify_all = true, // new to cargo-local-install
+            "--rebuild"     => cli.rebuild = true, // new to cargo-local-install
+            "--link-mode"   => options.link_mode = Some(args.next().ok_or_else(|| error!(Args, None, "--link-mode must specify a mode"))?.into().to_string_lossy().parse::<LinkMode>().map_err(|err| error!(Args, None, "--link-mode: {}", err))?), // new to cargo-local-install
+            "--show-log"    => cli.show_log = Some(args.next().ok_or_else(|| error!(Args, None, "--show-log must specify a crate"))?.into()), // new to cargo-local-install
+            "--color"       => cli.color = Some(args.next().ok_or_else(|| error!(Args, None, "--color must specify auto, always, or never"))?.into().to_string_lossy().into_owned()),
+            "--key-env"     => options.key_env.push(args.next().ok_or_else(|| error!(Args, None, "--key-env must specify an environment variable"))?.into()), // new to cargo-local-install
 
             // pass-through single-arg commands
-            "-q" | "--quiet" => {
-                log_mode = LogMode::Quiet;
-                options.push(InstallFlag::new(arg, Vec::new()));
-            },
-            "-v" | "--verbose" => {
-                log_mode = LogMode::Verbose;
-                options.push(InstallFlag::new(arg, Vec::new()));
-            },
-            "-j" | "--jobs" |
+            "-q" | "--quiet"    => options.verbosity = Verbosity::Quiet,
+            "-v" | "--verbose"  => options.verbosity = Verbosity::Verbose,
             "-f" | "--force" |
             "--all-features" | "--no-default-features" |
             "--debug" | "--bins" | "--examples"
             => {
-                options.push(InstallFlag::new(arg, Vec::new()));
+                options.flags.push(InstallFlag::new(arg, Vec::new()));
             },
 
             // pass-through single-arg commands
             "--version" |
             "--git" | "--branch" | "--tag" | "--rev" |
             "--profile" | "--target" |
-            "--index" | "--registry" |
-            "--color"
+            "--index" | "--registry"
             => {
-                let arg2 = args.next().ok_or_else(|| error!(None, "{} requires an argument", lossy))?.into();
-                options.push(InstallFlag::new(arg, vec![arg2]));
+                let arg2 = args.next().ok_or_else(|| error!(Args, None, "{} requires an argument", lossy))?.into();
+                options.flags.push(InstallFlag::new(arg, vec![arg2]));
             },
 
             // pass-through multi-arg commands
-            "--features"    => return Err(error!(None, "not yet implemented: {}", lossy)),
-            "--bin"         => return Err(error!(None, "not yet implemented: {}", lossy)),
-            "--example"     => return Err(error!(None, "not yet implemented: {}", lossy)),
+            "--features"    => return Err(error!(Args, None, "not yet implemented: {}", lossy)),
+            "--bin"         => return Err(error!(Args, None, "not yet implemented: {}", lossy)),
+            "--example"     => return Err(error!(Args, None, "not yet implemented: {}", lossy)),
 
             "--" => {
-                crates.extend(args.map(|a| a.into()));
+                options.tools.extend(args.map(|a| Tool::new(a)));
                 break;
             },
 
-            flag if flag.starts_with("-") => return Err(error!(None, "unrecognized flag: {}", flag)),
-            _krate => crates.push(arg),
+            flag if flag.starts_with("-") => return Err(error!(Args, None, "unrecognized flag: {}", flag)),
+            toolchain if toolchain.starts_with('+') => return Err(error!(Args, None, "{} must be the first argument", toolchain)),
+            _krate => options.tools.push(Tool::new(arg)),
         }
     }
-    let quiet   = log_mode == LogMode::Quiet;
-    let verbose = log_mode == LogMode::Verbose;
-
-    let locked = locked.unwrap_or_else(|| {
-        if !crates.is_empty() { warnln!("either specify --locked to use the same dependencies the crate was built with, or --unlocked to get rid of this warning"); }
-        false
-    });
-    if locked {
-        options.push(InstallFlag::new("--locked", Vec::new()));
-    }
 
-    let mut installs = if crates.is_empty() {
-        manifest::find_cwd_installs(maybe_dst_bin.clone()).map_err(|err| error!(None, "error enumerating Cargo.tomls: {}", err))?
-    } else {
-        vec![InstallSet {
-            bin:        maybe_dst_bin.clone().unwrap_or_else(|| PathBuf::from("bin")),
-            src:        None,
-            installs:   crates.into_iter().map(|c| Install { name: c, flags: vec![] }).collect(),
-        }]
-    };
-
-    if installs.is_empty() {
-        return Err(error!(None, "no crates specified"))
-    }
-
-    let global_dir = {
-        let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
-        let mut d = PathBuf::from(std::env::var_os(var).ok_or_else(|| error!(None, "couldn't determine target dir, {} not set", var))?);
-        d.push(".cargo");
-        d.push("local-install");
-        d
-    };
-    let crates_cache_dir = global_dir.join("crates");
+    Ok(false)
+}
 
-    let target_dir = target_dir.map_or_else(|| Ok(global_dir.join("target")), |td| canonicalize(td))?;
-    options.push(InstallFlag::new("--target-dir", vec![target_dir.into()]));
-    if let Some(path) = path { options.push(InstallFlag::new("--path", vec![canonicalize(path)?.into()])); }
-    options.sort();
+struct Context {
+    pub dry_run:            bool,
+    pub quiet:              bool,
+    pub verbose:            bool,
+    pub term:               Term,
+}
 
-    for set in installs.iter_mut() {
-        for install in set.installs.iter_mut() {
-            install.flags.extend(options.clone());
-            install.flags.sort();
+impl Install {
+    /// A canonical description of what this install builds, used to pick the `crates/<hash>` cache entry.
+    /// Presentation-only flags are excluded, and the rest are sorted and deduplicated, so e.g. `-q foo` and `foo` share
+    /// a single cached build, as do builds using different `--target-dir`s.  `build_env` (see [build_environment]) is
+    /// appended so builds with different `RUSTFLAGS`, targets, or toolchains never share a cache entry.
+    fn cache_key(&self, build_env: &str) -> String {
+        let mut flags = self.flags.iter().filter(|f| f.affects_build()).collect::<Vec<_>>();
+        flags.sort();
+        flags.dedup();
+
+        let mut key = String::from("cargo");
+        if let Some(toolchain) = self.toolchain.as_ref() { write!(&mut key, " +{}", toolchain).unwrap(); }
+        key.push_str(" install");
+        for InstallFlag { flag, args } in flags {
+            write!(&mut key, " {}", flag.to_string_lossy()).unwrap();
+            for arg in args.iter() { write!(&mut key, " {:?}", arg).unwrap(); }
         }
+        write!(&mut key, " -- {:?}", self.name).unwrap();
+        key.push('\n');
+        key.push_str(build_env);
+        key
     }
 
-    for set in installs.into_iter() {
-        let any_local  = set.any_local();
-        let any_remote = set.any_remote();
-        if set.installs.is_empty() { continue }
-        assert!(any_local || any_remote);
-
-        let built = set.bin.join(".built");
-
-        let up_to_date = if !any_remote {
-            false
-        } else if let Some(src) = set.src.as_ref() {
-            let src_mod = src.metadata().ok().and_then(|m| m.modified().ok());
-            let built_mod = built.metadata().ok().and_then(|m| m.modified().ok());
-
-            let up_to_date = match (src_mod, built_mod) {
-                (Some(src), Some(built))    => src < built,
-                _other                      => false,
-            };
+    /// The `crates/<hash>` directory this install is built into (via `cargo install --root ...`)
+    fn cache_dir(&self, crates_cache_dir: &Path, build_env: &str) -> PathBuf {
+        #[allow(deprecated)] let mut hasher = std::hash::SipHasher::new();
+        self.cache_key(build_env).hash(&mut hasher);
+        crates_cache_dir.join(format!("{:016x}", hasher.finish()))
+    }
+}
 
-            if up_to_date && !any_local {
-                if verbose { statusln!("Skipping", "`{}`: up to date", src.display()); }
-                continue
-            }
+/// A single `cargo install --root crates/<hash>` invocation, shared by every [Install] with the same cache key.
+struct Build {
+    name:       OsString,
+    flags:      Vec<InstallFlag>,
+    root:       PathBuf,
+    /// The `Cargo.toml` of the first set needing this build, if any, for errors.
+    set:        Option<PathBuf>,
+    toolchain:  Option<String>,
+    /// Pass `-Z no-index-update` (if another build already updated the index) to this build's cargo.
+    z_no_index_update_hack: bool,
+}
 
-            up_to_date
-        } else {
-            false
-        };
+/// Runs [Build]s, up to one per target directory at a time, since cargo serializes builds sharing a target directory.
+struct BuildPool {
+    target_dirs:            Vec<PathBuf>,
+    jobs:                   Option<usize>,
+    /// Keep starting builds after one fails (`--keep-going`).
+    keep_going:             bool,
+    /// Environment variables (or `PREFIX_*` patterns) not to pass on to cargo.
+    env_remove:             Vec<OsString>,
+    runner:                 Arc<dyn CargoRunner>,
+}
 
-        let mut first_install = true;
-        for install in set.installs.into_iter() {
-            if install.is_remote() {
-                if up_to_date { continue }
-            }
-            let context = Context {
-                dry_run, quiet, verbose,
-                z_no_index_update_hack: z_no_index_update_hack && !first_install,
-                crates_cache_dir: crates_cache_dir.as_path(),
-                dst_bin: set.bin.as_path()
-            };
-            install.install(context)?;
-            first_install = false;
-        }
-        if any_remote && set.src.is_some() {
-            std::fs::write(&built, "").map_err(|err| error!(err, "unable to create {}: {}", built.display(), err))?;
-        }
-    }
+impl BuildPool {
+    /// Run every build (or until the first error, without `--keep-going`), returning the result for each root built.
+    fn run(&self, context: &Context, builds: Vec<Build>) -> Vec<(PathBuf, std::time::Duration, Result<Outcome, Error>)> {
+        let parallel        = self.target_dirs.len() > 1;
+        let builds          = Mutex::new(builds.into_iter());
+        let results         = Mutex::new(Vec::new());
+        let failed          = AtomicBool::new(false);
+        let index_updated   = AtomicBool::new(false);
+
+        std::thread::scope(|scope| for target_dir in self.target_dirs.iter() {
+            let (builds, results, failed, index_updated) = (&builds, &results, &failed, &index_updated);
+            scope.spawn(move || loop {
+                if failed.load(Ordering::SeqCst) && !self.keep_going { break }
+                let build = match builds.lock().unwrap().next() { Some(b) => b, None => break };
+                let slot = BuildSlot {
+                    target_dir,
+                    jobs:                   self.jobs,
+                    prefix:                 if parallel { Some(format!("[{}]", build.name.to_string_lossy())) } else { None },
+                    // Only the first build needs to update the index, which the rest can then skip
+                    z_no_index_update_hack: build.z_no_index_update_hack && index_updated.load(Ordering::SeqCst),
+                    env_remove:             &self.env_remove,
+                    runner:                 &*self.runner,
+                };
+                let root = build.root.clone();
+                let build_start = std::time::Instant::now();
+                let (name, set) = (build.name.clone(), build.set.clone());
+                let result = build.run(context, slot).map_err(|err| err.with_tool(&name).with_set(set.as_deref()));
+                match result {
+                    Ok(_)   => index_updated.store(true, Ordering::SeqCst),
+                    Err(_)  => failed.store(true, Ordering::SeqCst),
+                }
+                results.lock().unwrap().push((root, build_start.elapsed(), result));
+            });
+        });
 
-    let stop = std::time::Instant::now();
-    if !quiet { statusln!("Finished", "installing crate(s) in {:.2}s", (stop-start).as_secs_f32()); }
-    if path_warning {
-        if let Some(dst_bin) = maybe_dst_bin {
-            warnln!("be sure to add `{}` to your PATH to be able to run the installed binaries", dst_bin.display());
-        } else {
-            warnln!("be sure to add `$crate\\bin` path(s) to your PATH to be able to run the installed binaries");
-        }
+        results.into_inner().unwrap()
     }
-    Ok(())
 }
 
-struct Context<'a> {
-    pub dry_run:            bool,
-    pub quiet:              bool,
-    pub verbose:            bool,
-    pub z_no_index_update_hack: bool,
-    pub crates_cache_dir:   &'a Path,
-    pub dst_bin:            &'a Path,
+/// The [BuildPool] resources handed to a single [Build].
+struct BuildSlot<'a> {
+    target_dir:             &'a Path,
+    jobs:                   Option<usize>,
+    prefix:                 Option<String>,
+    z_no_index_update_hack: bool,
+    env_remove:             &'a [OsString],
+    runner:                 &'a dyn CargoRunner,
 }
 
-impl Install {
-    fn install(self, context: Context) -> Result<(), Error> {
-        let Context { dry_run, quiet, verbose, z_no_index_update_hack, crates_cache_dir, dst_bin } = context;
+impl Build {
+    fn run(self, context: &Context, slot: BuildSlot) -> Result<Outcome, Error> {
+        let Context { dry_run, quiet, verbose, ref term } = *context;
+        let BuildSlot { target_dir, jobs, prefix, z_no_index_update_hack, env_remove, runner } = slot;
 
-        let mut trace = format!("cargo install");
-        let mut cmd = Command::new("cargo");
+        // Other processes might be building the same cache entry
+        let _lock = if dry_run { None } else {
+            if let Some(crates_cache_dir) = self.root.parent() {
+                std::fs::create_dir_all(crates_cache_dir).map_err(|err| error!(Cache, err, "unable to create {}: {}", crates_cache_dir.display(), err))?;
+            }
+            Some(FileLock::acquire(term, self.root.with_extension("lock"), &self.root, quiet)?)
+        };
+
+        // `cargo install` won't notice corrupted bins on its own.  This is the only time cached bins get hashed: linking
+        // (after this build) relies on it.
+        let checksums = if dry_run { None } else { Checksums::read(&self.root) };
+        let corrupted = checksums.as_ref().is_some_and(|checksums| {
+            let problems = checksums.check(&self.root);
+            for problem in problems.iter() { warnln!(term, "cached bin doesn't match its checksum, rebuilding: {}", problem); }
+            !problems.is_empty()
+        });
+
+        let krate = self.name.to_string_lossy().into_owned();
+        let mut trace = String::from("cargo");
+        if let Some(toolchain) = self.toolchain.as_ref() { write!(&mut trace, " +{}", toolchain).unwrap(); }
+        trace.push_str(" install");
+        let mut cmd = cargo_command(self.toolchain.as_deref());
         cmd.arg("install");
         for InstallFlag { flag, args } in self.flags {
             write!(&mut trace, " {}", flag.to_str().unwrap()).unwrap();
@@ -367,136 +552,218 @@ impl Install {
             }
         }
 
-        let hash = {
-            // real trace will have "--root ...", but that depends on hash!
-            let trace_for_hash = format!("{} -- {}", trace, self.name.to_string_lossy());
-            #[allow(deprecated)] let mut hasher = std::hash::SipHasher::new();
-            trace_for_hash.hash(&mut hasher);
-            format!("{:016x}", hasher.finish())
-        };
+        write!(&mut trace, " --target-dir {:?}", target_dir.display()).unwrap();
+        cmd.arg("--target-dir").arg(target_dir);
 
-        let krate_build_dir = crates_cache_dir.join(hash);
-        write!(&mut trace, " --root {:?}", krate_build_dir.display()).unwrap();
-        cmd.arg("--root").arg(&krate_build_dir);
+        if let Some(jobs) = jobs {
+            write!(&mut trace, " --jobs {}", jobs).unwrap();
+            cmd.arg("--jobs").arg(jobs.to_string());
+        }
+
+        write!(&mut trace, " --root {:?}", self.root.display()).unwrap();
+        cmd.arg("--root").arg(&self.root);
 
-        write!(&mut trace, " --color always").unwrap();
-        cmd.arg("--color").arg("always");
+        write!(&mut trace, " --color {}", term.cargo_color()).unwrap();
+        cmd.arg("--color").arg(term.cargo_color());
 
         if z_no_index_update_hack {
             cmd.arg("-Z").arg("no-index-update");
             cmd.env("__CARGO_TEST_CHANNEL_OVERRIDE_DO_NOT_USE_THIS", "nightly"); // *cackles manically*
         }
 
+        if corrupted {
+            trace.push_str(" --force");
+            cmd.arg("--force");
+        }
+
+        if term.json() {
+            trace.push_str(" --message-format json");
+            cmd.arg("--message-format").arg("json");
+        }
+
+        for (name, _) in std::env::vars_os().filter(|(name, _)| env_matches(name, env_remove)) { cmd.env_remove(name); }
+
         trace.push_str(" -- ");
         trace.push_str(&self.name.to_string_lossy());
         cmd.arg("--");
         cmd.arg(self.name);
 
         if dry_run {
-            statusln!("Skipping", "`{}` (--dry-run)", trace);
-            return Ok(()); // XXX: Would be nice to log copied bins, but without building them we don't know what they are
+            statusln!(term, "Skipping", "`{}` (--dry-run)", trace);
+            return Ok(Outcome::Skipped); // XXX: Would be nice to log copied bins, but without building them we don't know what they are
         } else if verbose {
-            statusln!("Running", "`{}`", trace);
+            statusln!(term, "Running", "`{}`", trace);
         }
 
+        let bins_before = if corrupted { None } else { bins_modified(&self.root) };
+        Event::new("install-started").str("crate", &krate).path("root", &self.root).str("command", &trace).emit(term);
+
+        let build_start = std::time::Instant::now();
         cmd.stderr(Stdio::piped());
-        let mut cmd = cmd.spawn().map_err(|err| error!(err, "failed to spawn {}: {}", trace, err))?;
-        let stderr_thread = cmd.stderr.take().map(|stderr| std::thread::spawn(|| filter_stderr(stderr)));
-        let status = cmd.wait();
+        if term.json() { cmd.stdout(Stdio::piped()); }
+        let process = runner.spawn(&mut cmd).map_err(|err| error!(Spawn, err, "failed to spawn {}: {}", trace, err))?;
+        let log_path = self.root.with_extension("log");
+        let log = std::fs::File::create(&log_path).map_err(|err| warnln!(term, "unable to create build log {}: {}", log_path.display(), err)).ok();
+        if log.is_some() { record_last_log(term, &self.root, &krate, &log_path) }
+        let filter = StderrFilter { prefix: prefix.clone(), root: self.root.display().to_string(), krate: krate.clone(), version: None };
+        let json_log = log.as_ref().and_then(|log| log.try_clone().ok()); // sharing its offset, so writes interleave
+        let stderr_thread = process.stderr.map(|stderr| { let term = term.clone(); std::thread::spawn(move || filter_stderr(&term, stderr, filter, log, quiet)) });
+        let stdout_thread = process.stdout.map(|stdout| {
+            let (term, krate, prefix) = (term.clone(), krate.clone(), prefix.clone());
+            std::thread::spawn(move || forward_cargo_json(&term, stdout, &krate, json_log, prefix, quiet))
+        });
+        let status = (process.wait)();
         let _stderr_thread = stderr_thread.map(|t| t.join());
-        let status = status.map_err(|err| error!(err, "failed to execute {}: {}", trace, err))?;
-        match status.code() {
-            Some(0) => { if verbose { statusln!("Succeeded", "`{}`", trace) } },
-            Some(n) => return Err(error!(None, "{} failed (exit code {})", trace, n)),
-            None    => return Err(error!(None, "{} failed (signal)", trace)),
-        }
-
-        if let Err(err) = std::fs::create_dir_all(&dst_bin) {
-            if !quiet {
-                warnln!("Unable to create directory `{}`: {}", dst_bin.display(), err);
-            }
-        } else if verbose {
-            statusln!("Created", "`{}\\`", dst_bin.display());
+        let _stdout_thread = stdout_thread.map(|t| t.join());
+        let code = status.map_err(|err| error!(Spawn, err, "failed to execute {}: {}", trace, err))?;
+        let mut finished = Event::new("build-finished").str("crate", &krate).path("root", &self.root).bool("success", code == Some(0)).num("duration", build_start.elapsed().as_secs_f64());
+        if let Some(code) = code { finished = finished.num("exit_code", code); }
+        finished.emit(term);
+        match code {
+            Some(0) => { if verbose { statusln!(term, "Succeeded", "`{}`", trace) } },
+            Some(n) => { if quiet { print_log_tail(term, &log_path, prefix.as_deref()) } return Err(error!(BuildFailed { exit_code: Some(n) }, None, "{} failed (exit code {}), see {}", trace, n, log_path.display())) },
+            None    => { if quiet { print_log_tail(term, &log_path, prefix.as_deref()) } return Err(error!(BuildFailed { exit_code: None }, None, "{} failed (signal), see {}", trace, log_path.display())) },
         }
-
-        let src_bin_path = krate_build_dir.join("bin");
-        let src_bins = src_bin_path.read_dir().map_err(|err| error!(err, "unable to enumerate source bins at {}: {}", src_bin_path.display(), err))?;
-        for src_bin in src_bins {
-            let src_bin = src_bin.map_err(|err| error!(err, "error enumerating source bins at {}: {}", src_bin_path.display(), err))?;
-            let dst_bin = dst_bin.join(src_bin.file_name());
-            let file_type = src_bin.file_type().map_err(|err| error!(err, "error determining file type for {}: {}", src_bin.path().display(), err))?;
-            if !file_type.is_file() { continue }
-            let src_bin = src_bin.path();
-
-            if verbose { statusln!("Replacing", "`{}`", dst_bin.display()) }
-            #[cfg(windows)] {
-                let _ = std::fs::remove_file(&dst_bin);
-                if let Err(err) = std::os::windows::fs::symlink_file(&src_bin, &dst_bin) {
-                    if !quiet { warnln!("Unable to link `{}` to `{}`: {}", dst_bin.display(), src_bin.display(), err) }
-                } else {
-                    if verbose { statusln!("Linked", "`{}` to `{}`", dst_bin.display(), src_bin.display()) }
-                    continue
-                }
-            }
-            #[cfg(unix)] {
-                let _ = std::fs::remove_file(&dst_bin);
-                if let Err(err) = std::os::unix::fs::symlink(&src_bin, &dst_bin) {
-                    if !quiet { warnln!("Unable to link `{}` to `{}`: {}", dst_bin.display(), src_bin.display(), err) }
-                } else {
-                    if verbose { statusln!("Linked", "`{}` to `{}`", dst_bin.display(), src_bin.display()) }
-                    continue
-                }
-            }
-            std::fs::copy(&src_bin, &dst_bin).map_err(|err| error!(err, "error replacing `{}` with `{}`: {}", dst_bin.display(), src_bin.display(), err))?;
-            if !quiet { statusln!("Replaced", "`{}` with `{}`", dst_bin.display(), src_bin.display()) }
-        }
-
-        Ok(())
+        // cargo leaves bins alone when it decides they're already installed
+        let cached = bins_before.is_some() && bins_before == bins_modified(&self.root);
+        if cached { Event::new("cache-hit").str("crate", &krate).path("root", &self.root).emit(term); }
+        if !cached || checksums.is_none() { Checksums::record(&self.root)?; } // cache hits were just checked above
+        Ok(if cached { Outcome::Cached } else { Outcome::Built })
     }
 }
 
-struct Ignore {
-    /// ASCII prefix
-    pre:    &'static str,
+/// Where the path of `krate`'s last build log is recorded for `--show-log`: `logs/<crate>` next to `crates/`.
+fn last_log_pointer(crates_cache_dir: &Path, krate: &str) -> PathBuf { crates_cache_dir.with_file_name("logs").join(krate) }
 
-    /// ANSI colored prefix
-    prec:   &'static str,
+fn record_last_log(term: &Term, root: &Path, krate: &str, log_path: &Path) {
+    let pointer = last_log_pointer(root.parent().unwrap_or(root), krate);
+    let written = pointer.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|()| std::fs::write(&pointer, format!("{}\n", log_path.display())));
+    if let Err(err) = written { warnln!(term, "unable to record build log in {}: {}", pointer.display(), err); }
+}
 
-    /// postfix
-    post:   &'static str,
+/// The latest modification time of any bin in `root/bin`, if any.
+fn bins_modified(root: &Path) -> Option<std::time::SystemTime> {
+    root.join("bin").read_dir().ok()?.filter_map(|e| e.ok()?.metadata().ok()?.modified().ok()).max()
 }
 
-static IGNORE : &'static [Ignore] = &[
-    // We spam reinstalls for already installed stuff
-    Ignore { pre: "     Ignored package `", post: "` is already installed, use --force to override", prec: "\u{1b}[0m\u{1b}[0m\u{1b}[1m\u{1b}[32m     Ignored\u{1b}[0m package `" },
+/// Rewrites or filters out cargo's stderr lines, which are classified by their uncolored status verb and text:
+///
+/// - "Ignored package `...` is already installed": we spam reinstalls for already installed stuff.
+/// - "warning: be sure to add `...\\local-install\\crates\\e5ce6d367e4d6f3f\\bin` to your PATH": we roll our own.
+/// - "Finished ...": don't spam this per-crate, that's silly, roll our own for the final output.
+///
+/// Anything else is forwarded, with the internal cache path rewritten to a friendlier `<cache>/name-version`.
+struct StderrFilter {
+    /// Prepended to every line when multiple builds are interleaved by `--parallel` (typically the crate name).
+    prefix:     Option<String>,
+    /// The cache entry being installed into, as cargo will display it.
+    root:       String,
+    krate:      String,
+    /// Picked up from cargo's "Installing name vX.Y.Z" line.
+    version:    Option<String>,
+}
 
-    // We spam "internal" .cargo\local-install paths
-    Ignore { pre: "warning: be sure to add `", post: "` to your PATH to be able to run the installed binaries", prec: "\x1B[0m\x1B[0m\x1B[1m\x1B[33mwarning\x1B[0m\x1B[1m:\x1B[0m be sure to add `" },
-    Ignore { pre: "   Replacing ", post: "", prec: "\u{1b}[0m\u{1b}[0m\u{1b}[1m\u{1b}[32m   Replacing\u{1b}[0m " },
-    Ignore { pre: "    Replaced ", post: "", prec: "\u{1b}[0m\u{1b}[0m\u{1b}[1m\u{1b}[32m    Replaced\u{1b}[0m " },
+impl StderrFilter {
+    fn filter(&mut self, line: &str) -> Option<String> {
+        let plain = strip_ansi(line);
+        let plain = plain.trim();
+        let mut words = plain.split_whitespace();
+        let verb = words.next().unwrap_or("");
+
+        match verb {
+            "Ignored"   if plain.ends_with("is already installed, use --force to override") => return None,
+            "warning:"  if plain.starts_with("warning: be sure to add `") && plain.contains("to your PATH") => return None,
+            "Finished"  => return None,
+            "Installing" => if let (Some(name), Some(version)) = (words.next(), words.next()) {
+                if name == self.krate && version.starts_with('v') { self.version = Some(version[1..].to_string()); }
+            },
+            _other => {},
+        }
 
-    // Don't spam this per-crate that's silly, roll our own for the final output
-    Ignore { pre: "    Finished ", post: "", prec: "\u{1b}[0m\u{1b}[0m\u{1b}[1m\u{1b}[32m    Finished\u{1b}[0m " },
+        let line = if !self.root.is_empty() && line.contains(&self.root) {
+            let friendly = match self.version.as_ref() {
+                Some(version)   => format!("<cache>/{}-{}", self.krate, version),
+                None            => format!("<cache>/{}", self.krate),
+            };
+            line.replace(&self.root, &friendly)
+        } else {
+            line.to_string()
+        };
 
-    // Okay, we'll let *this* spam through...
-    //Ignore { pre: "  Installing ", post: "", prec: "\u{1b}[0m\u{1b}[0m\u{1b}[1m\u{1b}[32m  Installing\u{1b}[0m " },
-];
+        Some(match self.prefix.as_ref() {
+            Some(prefix)    => format!("{} {}", prefix, line),
+            None            => line,
+        })
+    }
+}
 
+/// Remove ANSI escape sequences (CSI sequences like `\x1B[1;32m`, and two-byte escapes) from `line`.
+fn strip_ansi(line: &str) -> String {
+    let mut o = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\u{1B}' { o.push(ch); continue }
+        match chars.next() {
+            Some('[')   => { let _ = chars.by_ref().find(|ch| ('\u{40}'..='\u{7E}').contains(ch)); },
+            _other      => {},
+        }
+    }
+    o
+}
 
+/// Every line goes to `log` (sans ANSI escapes), but is only forwarded (if not filtered out) when not `quiet`.
+fn filter_stderr(term: &Term, input: impl io::Read, mut filter: StderrFilter, mut log: Option<std::fs::File>, quiet: bool) -> io::Result<()> {
+    use std::io::Write as _;
+    for line in BufReader::new(input).lines() {
+        let line = line?;
+        if let Some(log) = log.as_mut() { let _ = writeln!(log, "{}", strip_ansi(&line)); }
+        if quiet { continue }
+        if let Some(line) = filter.filter(&line) { term.line(&line); }
+    }
+    Ok(())
+}
+
+/// Show what went wrong with a `--quiet` build, which otherwise wouldn't have been shown at all.
+fn print_log_tail(term: &Term, log: &Path, prefix: Option<&str>) {
+    const TAIL : usize = 20;
+    let text = match std::fs::read_to_string(log) { Ok(t) => t, Err(_) => return };
+    let lines = text.lines().collect::<Vec<_>>();
+    for line in lines[lines.len().saturating_sub(TAIL)..].iter() {
+        match prefix {
+            Some(prefix)    => term.line(&format!("{} {}", prefix, line)),
+            None            => term.line(line),
+        }
+    }
+}
 
-/// Filters out bad warnings like:
-/// "\u{1b}[0m\u{1b}[0m\u{1b}[1m\u{1b}[33mwarning\u{1b}[0m\u{1b}[1m:\u{1b}[0m be sure to add `C:\\Users\\Name\\.cargo\\local-install\\crates\\e5ce6d367e4d6f3f\\bin` to your PATH to be able to run the installed binaries"
-fn filter_stderr(input: std::process::ChildStderr) -> io::Result<()> {
+/// Forward cargo's own `--message-format json` messages from its stdout as `cargo-message` events.  Since that also
+/// moves rustc's diagnostics from stderr to stdout, their `rendered` text is logged and shown (unless `quiet`) as
+/// [filter_stderr] would have.
+fn forward_cargo_json(term: &Term, input: impl io::Read, krate: &str, mut log: Option<std::fs::File>, prefix: Option<String>, quiet: bool) -> io::Result<()> {
+    use std::io::Write as _;
     for line in BufReader::new(input).lines() {
         let line = line?;
-        if IGNORE.iter().any(|ignore| line.ends_with(ignore.post) && (line.starts_with(ignore.pre) || line.starts_with(ignore.prec))) { continue }
-        eprintln!("{}", line);
+        let line = line.trim();
+        if !line.starts_with('{') { continue }
+        Event::new("cargo-message").str("crate", krate).raw("message", line).emit(term);
+
+        if !line.starts_with("{\"reason\":\"compiler-message\"") { continue }
+        const RENDERED : &str = "\"rendered\":";
+        let rendered = match line.rfind(RENDERED).and_then(|i| events::unquote(&line[i + RENDERED.len() ..])) { Some(r) => r, None => continue };
+        for rendered in rendered.trim_end().lines() {
+            if let Some(log) = log.as_mut() { let _ = writeln!(log, "{}", strip_ansi(rendered)); }
+            if quiet { continue }
+            match prefix.as_ref() {
+                Some(prefix)    => term.line(&format!("{} {}", prefix, rendered)),
+                None            => term.line(rendered),
+            }
+        }
     }
     Ok(())
 }
 
 fn help() -> Result<(), Error> {
-    print_usage(&mut std::io::stdout().lock()).map_err(|err| error!(err, "unable to write help text to stdout: {}", err))
+    print_usage(&mut std::io::stdout().lock()).map_err(|err| error!(Io, err, "unable to write help text to stdout: {}", err))
 }
 
 fn print_usage(mut o: impl io::Write) -> io::Result<()> {
@@ -505,8 +772,8 @@ fn print_usage(mut o: impl io::Write) -> io::Result<()> {
     writeln!(o, "Install a Rust binary. Default installation location is ./bin")?;
     writeln!(o)?;
     writeln!(o, "USAGE:")?;
-    writeln!(o, "    cargo local-install [OPTIONS] [--] [crate]...")?;
-    writeln!(o, "    cargo-local-install [OPTIONS] [--] [crate]...")?;
+    writeln!(o, "    cargo local-install [+toolchain] [OPTIONS] [--] [crate]...")?;
+    writeln!(o, "    cargo-local-install [+toolchain] [OPTIONS] [--] [crate]...")?;
     writeln!(o)?;
     writeln!(o, "OPTIONS:")?;
     // pass-through options to `cargo install`
@@ -518,6 +785,7 @@ fn print_usage(mut o: impl io::Write) -> io::Result<()> {
     writeln!(o, "        --path <PATH>                                Filesystem path to local crate to install")?;
     // writeln!(o, "        --list                                       list all installed packages and their versions // not supported
     writeln!(o, "    -j, --jobs <N>                                   Number of parallel jobs, defaults to # of CPUs")?;
+    writeln!(o, "        --parallel <N>                               Number of crates to build at once (splits --jobs between them)")?;
     writeln!(o, "    -f, --force                                      Force overwriting existing crates or binaries")?;
     // writeln!(o, "        --no-track                                   Do not save tracking information")?; // not supported
     // writeln!(o, "        --features <FEATURES>...                     Space or comma separated list of features to activate")?; // nyi
@@ -531,8 +799,10 @@ fn print_usage(mut o: impl io::Write) -> io::Result<()> {
     writeln!(o, "        --examples                                   Install all examples")?;
     writeln!(o, "        --target <TRIPLE>                            Build for the target triple")?;
     writeln!(o, "        --target-dir <DIRECTORY>                     Directory for all generated artifacts")?;
+    writeln!(o, "        --cache-dir <DIRECTORY>                      Directory for cached builds, defaults to $CARGO_HOME/local-install")?;
     writeln!(o, "        --root <DIR>                                 Install package bins into <DIR>/bin")?;
     writeln!(o, "        --out-bin <DIR>                              Install package bins into <DIR>")?;
+    writeln!(o, "        --link-mode <MODE>                           How to install bins: auto, symlink, relative-symlink, hardlink, copy, shim")?;
     writeln!(o, "        --index <INDEX>                              Registry index to install from")?;
     writeln!(o, "        --registry <REGISTRY>                        Registry to use")?;
     writeln!(o, "    -v, --verbose                                    Use verbose output (-vv very verbose/build.rs output)")?;
@@ -544,6 +814,13 @@ fn print_usage(mut o: impl io::Write) -> io::Result<()> {
     writeln!(o, "        --unlocked                                   Don't require an up-to-date Cargo.lock")?;
     writeln!(o, "        --dry-run                                    Print `cargo install ...` spam but don't actually install")?;
     writeln!(o, "        --no-path-warning                            Don't remind the user to add `bin` to their PATH")?;
+    writeln!(o, "        --message-format <FMT>                       Also print JSON events to stdout: human, json")?;
+    writeln!(o, "        --keep-going                                 Keep installing other crates after one fails")?;
+    writeln!(o, "        --verify                                     Check cached bins and links against recorded checksums instead of installing")?;
+    writeln!(o, "        --all                                        With --verify: check every cache entry, not just this project's")?;
+    writeln!(o, "        --rebuild                                    With --verify: remove mismatched cache entries, then rebuild and relink")?;
+    writeln!(o, "        --show-log <CRATE>                           Print the build log from the last install of <CRATE>")?;
+    writeln!(o, "        --key-env <VAR>...                           Also cache separately by this env var (`*` matches anything)")?;
     // writeln!(o, "    -Z <FLAG>...")?; // nyi
     writeln!(o)?;
     writeln!(o, "ARGS:")?;
@@ -565,15 +842,77 @@ fn print_usage(mut o: impl io::Write) -> io::Result<()> {
     Ok(())
 }
 
+/// Environment variables that change what `cargo install` builds, and thus get included in the cache key.
+/// `*` matches any run of characters.  Extended by `--key-env <VAR>`.
+const DEFAULT_KEY_ENV : &[&str] = &[
+    "RUSTC",
+    "RUSTFLAGS",
+    "CARGO_ENCODED_RUSTFLAGS",
+    "CARGO_BUILD_RUSTC",
+    "CARGO_BUILD_RUSTFLAGS",
+    "CARGO_BUILD_TARGET",
+    "CARGO_PROFILE_*",
+    "CARGO_TARGET_*_RUSTFLAGS",
+    "CARGO_TARGET_*_LINKER",
+];
+
+/// `cargo +toolchain` (via rustup's `cargo` proxy) if a toolchain was requested, otherwise `$CARGO` (set for subcommands
+/// and build scripts to the cargo running them), otherwise whichever `cargo` is on `PATH`.  `$CARGO` is ignored for
+/// toolchains since it names a toolchain's own cargo, which doesn't understand `+toolchain`: see the Readme.
+fn cargo_command(toolchain: Option<&str>) -> Command {
+    match toolchain {
+        Some(toolchain) => { let mut cmd = Command::new("cargo"); cmd.arg(format!("+{}", toolchain)); cmd },
+        None            => Command::new(std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into())),
+    }
+}
+
+/// Describes the parts of the build environment that affect built binaries: the value of every environment variable
+/// matching `key_env`, and `rustc -vV` (version, commit hash, host triple) of the compiler cargo will use.
+fn build_environment(runner: &dyn CargoRunner, key_env: &[OsString], env_remove: &[OsString], toolchain: Option<&str>) -> String {
+    let mut vars = std::env::vars_os().filter(|(name, _)| env_matches(name, key_env) && !env_matches(name, env_remove)).collect::<Vec<_>>();
+    vars.sort();
+
+    let mut env = String::new();
+    for (name, value) in vars {
+        writeln!(&mut env, "{}={:?}", name.to_string_lossy(), value).unwrap();
+    }
+
+    let mut rustc = match std::env::var_os("RUSTC").filter(|_| !env_matches(OsStr::new("RUSTC"), env_remove)) {
+        Some(rustc) => Command::new(rustc), // used by cargo regardless of toolchain
+        None        => { let mut cmd = Command::new("rustc"); if let Some(toolchain) = toolchain { cmd.arg(format!("+{}", toolchain)); } cmd },
+    };
+    match runner::output(runner, rustc.arg("-vV").stderr(Stdio::null()).stdout(Stdio::piped())) {
+        Ok((o, Some(0))) => {
+            for line in o.lines().filter(|l| !l.trim().is_empty() && !l.starts_with("binary:")) { writeln!(&mut env, "{}", line.trim()).unwrap(); }
+        },
+        _other => env.push_str("rustc unknown\n"),
+    }
+    env
+}
+
+/// Does `name` match any of `patterns` (either exactly, or with wildcards like `CARGO_TARGET_*_LINKER`)?
+fn env_matches(name: &OsStr, patterns: &[OsString]) -> bool {
+    let name = name.to_string_lossy();
+    patterns.iter().any(|pattern| manifest::wildcard_matches(&pattern.to_string_lossy(), &name))
+}
+
 #[allow(dead_code)]
 fn version() {
     // TODO: (git hash, mod status, date) via build.rs nonsense?
     println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
 }
 
+fn parse_count(flag: &str, arg: Option<impl Into<OsString>>) -> Result<usize, Error> {
+    let arg = arg.ok_or_else(|| error!(Args, None, "{} requires an argument", flag))?.into();
+    match arg.to_string_lossy().parse() {
+        Ok(0) | Err(_)  => Err(error!(Args, None, "{} requires a positive integer, got {:?}", flag, arg)),
+        Ok(n)           => Ok(n),
+    }
+}
+
 fn canonicalize(path: impl AsRef<Path>) -> Result<PathBuf, Error> {
     let path = path.as_ref();
-    let path = std::fs::canonicalize(path).map_err(|err| error!(err, "unable to canonicalize {}: {}", path.display(), err))?;
+    let path = std::fs::canonicalize(path).map_err(|err| error!(Io, err, "unable to canonicalize {}: {}", path.display(), err))?;
     let mut o = PathBuf::new();
     for component in path.components() {
         if let Component::Prefix(pre) = component {
@@ -0,0 +1,343 @@
+//! Linking (or copying) built bins from the global cache into a project's `bin` directory.
+
+use super::*;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+
+
+/// How bins in `crates/<hash>/bin` are made available in `bin`.  Selected with `--link-mode` or `link-mode = "..."`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkMode {
+    /// Symlink, falling back on a copy if that fails (e.g. Windows without developer mode)
+    Auto,
+    /// Absolute symlink
+    Symlink,
+    /// Relative symlink, which survives bind mounting both the project and the cache into a container
+    RelativeSymlink,
+    /// Hard link, requiring the cache and the project to be on the same filesystem
+    Hardlink,
+    /// Plain copy
+    Copy,
+    /// Launcher scripts (`sh`, plus `.cmd` for `.exe`s) which set the `env` and prepend the `args` from metadata
+    Shim,
+}
+
+impl LinkMode {
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            LinkMode::Auto              => "auto",
+            LinkMode::Symlink           => "symlink",
+            LinkMode::RelativeSymlink   => "relative-symlink",
+            LinkMode::Hardlink          => "hardlink",
+            LinkMode::Copy              => "copy",
+            LinkMode::Shim              => "shim",
+        }
+    }
+}
+
+impl Display for LinkMode { fn fmt(&self, fmt: &mut Formatter) -> fmt::Result { fmt.write_str(self.as_str()) } }
+
+impl FromStr for LinkMode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto"              => Ok(LinkMode::Auto),
+            "symlink"           => Ok(LinkMode::Symlink),
+            "relative-symlink"  => Ok(LinkMode::RelativeSymlink),
+            "hardlink"          => Ok(LinkMode::Hardlink),
+            "copy"              => Ok(LinkMode::Copy),
+            "shim"              => Ok(LinkMode::Shim),
+            other               => Err(format!("unknown link mode {:?}, expected one of: auto, symlink, relative-symlink, hardlink, copy, shim", other)),
+        }
+    }
+}
+
+
+
+/// How each crate was last linked into a `bin` directory (link mode + cache entry), remembered in `bin/.links` so a
+/// changed mode or cache entry (e.g. after changing `RUSTFLAGS`) replaces existing links and shims, even when `.built`
+/// is otherwise up to date.
+pub(super) struct LinkRecords(BTreeMap<String, String>);
+
+impl LinkRecords {
+    fn path(dst_bin: &Path) -> PathBuf { dst_bin.join(".links") }
+
+    pub(super) fn read(dst_bin: &Path) -> Self {
+        let text = std::fs::read_to_string(Self::path(dst_bin)).unwrap_or_default();
+        Self(text.lines().filter_map(|line| {
+            let (name, record) = line.split_at(line.find('=')?);
+            Some((name.to_string(), record[1..].to_string()))
+        }).collect())
+    }
+
+    pub(super) fn matches(&self, link: &Link) -> bool {
+        self.0.get(&*link.name.to_string_lossy()) == Some(&Self::record(link))
+    }
+
+    /// Every recorded `(crate name, link mode, cache entry)`.
+    pub(super) fn entries(&self) -> Vec<(String, LinkMode, PathBuf)> {
+        self.0.iter().filter_map(|(name, record)| { let (mode, root) = Self::parse(record)?; Some((name.clone(), mode, root)) }).collect()
+    }
+
+    /// The recorded `(link mode, cache entry)` of the crate `name`, if any.
+    fn get(&self, name: &OsStr) -> Option<(LinkMode, PathBuf)> { self.0.get(&*name.to_string_lossy()).and_then(|record| Self::parse(record)) }
+
+    fn parse(record: &str) -> Option<(LinkMode, PathBuf)> {
+        let (mode, root) = record.split_at(record.find(' ')?);
+        Some((mode.parse().ok()?, PathBuf::from(&root[1..])))
+    }
+
+    fn record(link: &Link) -> String { format!("{} {}", link.mode, link.root.display()) }
+
+    fn write(&self, dst_bin: &Path) -> io::Result<()> {
+        let mut text = String::new();
+        for (name, record) in self.0.iter() { writeln!(&mut text, "{}={}", name, record).unwrap(); }
+        std::fs::write(Self::path(dst_bin), text)
+    }
+}
+
+
+
+/// Bins built into `root` (`crates/<hash>`) to link into `dst_bin` for the crate `name`.
+pub(super) struct Link {
+    pub name:       OsString,
+    pub root:       PathBuf,
+    pub dst_bin:    PathBuf,
+    pub mode:       LinkMode,
+    pub shim:       Shim,
+    /// The `Cargo.toml` that requested this link, if any.
+    pub set:        Option<PathBuf>,
+}
+
+impl Link {
+    /// Is any expected link in `dst_bin` missing or dangling?  (e.g. because someone deleted `crates/<hash>` by hand)
+    pub(super) fn is_dangling(&self) -> bool {
+        let src_bin = self.root.join("bin");
+        if !src_bin.is_dir() { return true }
+        let files = match Checksums::read(&self.root) {
+            Some(checksums) => checksums.bins().map(|(file, _)| OsString::from(file)).collect(),
+            None            => bin_files(&self.root), // entries predating `.checksums`
+        };
+        files.iter().any(|file| {
+            let dst = if self.mode == LinkMode::Shim { self.dst_bin.join(shim_name(file)) } else { self.dst_bin.join(file) };
+            !src_bin.join(file).exists() || !dst.exists()
+        })
+    }
+
+    /// The `(link, target)`s [link_bins] would create (or, if up to date, already created) for the bins in the cache.
+    pub(super) fn expected_links(&self) -> Vec<(PathBuf, PathBuf)> {
+        let src_bin = self.root.join("bin");
+        let mut links = src_bin.read_dir().map(|dir| dir.filter_map(|e| e.ok()).filter(|e| e.file_type().is_ok_and(|ft| ft.is_file())).map(|e| {
+            let file = e.file_name();
+            let dst = if self.mode == LinkMode::Shim { self.dst_bin.join(shim_name(&file)) } else { self.dst_bin.join(&file) };
+            (dst, src_bin.join(file))
+        }).collect::<Vec<_>>()).unwrap_or_default();
+        links.sort();
+        links
+    }
+}
+
+/// Link (or copy) every bin in `crates/<hash>/bin` into `dst_bin`, returning each `(link, target)` created.  The bins
+/// must already have been checked against their checksums by the [Build] that (re)built them.
+pub(super) fn link_bins(context: &Context, link: &Link) -> Result<Vec<(PathBuf, PathBuf)>, Error> {
+    let Context { quiet, verbose, ref term, .. } = *context;
+    let Link { name, root, dst_bin, mode, shim, .. } = link;
+    let mode = *mode;
+
+    if let Err(err) = std::fs::create_dir_all(dst_bin) {
+        if !quiet {
+            warnln!(term, "Unable to create directory `{}`: {}", dst_bin.display(), err);
+        }
+    } else if verbose {
+        statusln!(term, "Created", "`{}\\`", dst_bin.display());
+    }
+
+    // Other processes might be linking into the same bin directory
+    let _lock = FileLock::acquire(term, dst_bin.join(".lock"), dst_bin, quiet)?;
+    let mut records = LinkRecords::read(dst_bin);
+
+    // Links from the previous mode which this one won't replace (e.g. a `tool.exe` that'd shadow a new `tool.cmd` shim)
+    if let Some((old_mode, old_root)) = records.get(name).filter(|(old_mode, _)| *old_mode != mode) {
+        let names = |mode: LinkMode, roots: &[&Path]| roots.iter().flat_map(|root| bin_files(root)).flat_map(|file| link_names(mode, &file)).collect::<BTreeSet<_>>();
+        let new = names(mode, &[root]);
+        for stale in names(old_mode, &[&old_root, root]).difference(&new) {
+            let stale = dst_bin.join(stale);
+            match std::fs::remove_file(&stale) {
+                Ok(()) => if verbose { statusln!(term, "Removed", "`{}` (was {})", stale.display(), old_mode) },
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {},
+                Err(err) => return Err(error!(Link, err, "unable to remove `{}`, linked with the previous link mode ({}): {}", stale.display(), old_mode, err)),
+            }
+        }
+    }
+
+    let mut linked_bins = Vec::new();
+    let src_bin_path = root.join("bin");
+    let src_bins = src_bin_path.read_dir().map_err(|err| error!(Link, err, "unable to enumerate source bins at {}: {}", src_bin_path.display(), err))?;
+    for src_bin in src_bins {
+        let src_bin = src_bin.map_err(|err| error!(Link, err, "error enumerating source bins at {}: {}", src_bin_path.display(), err))?;
+        let file_name = src_bin.file_name();
+        let file_type = src_bin.file_type().map_err(|err| error!(Link, err, "error determining file type for {}: {}", src_bin.path().display(), err))?;
+        if !file_type.is_file() { continue }
+        let src_bin = src_bin.path();
+
+        if mode == LinkMode::Shim {
+            write_shims(context, name, &src_bin, dst_bin, shim, &mut linked_bins)?;
+            continue;
+        }
+
+        // Link/copy under a temporary name, then rename into place: `dst_bin` is never observed missing or truncated.
+        let tmp_bin = dst_bin.join(format!(".{}.{}.tmp", file_name.to_string_lossy(), std::process::id()));
+        let dst_bin = dst_bin.join(file_name);
+        let _ = std::fs::remove_file(&tmp_bin);
+
+        if verbose { statusln!(term, "Replacing", "`{}`", dst_bin.display()) }
+        let linked = match link_file(mode, &src_bin, &tmp_bin) {
+            Ok(linked) => linked,
+            Err(err) if mode == LinkMode::Auto => {
+                if !quiet { warnln!(term, "Unable to link `{}` to `{}`: {}", dst_bin.display(), src_bin.display(), err) }
+                link_file(LinkMode::Copy, &src_bin, &tmp_bin).map_err(|err| {
+                    let _ = std::fs::remove_file(&tmp_bin);
+                    error!(Link, err, "error copying `{}` to `{}`: {}", src_bin.display(), tmp_bin.display(), err)
+                })?
+            },
+            Err(err) => {
+                let _ = std::fs::remove_file(&tmp_bin);
+                return Err(error!(Link, err, "unable to link `{}` to `{}` ({}): {}", dst_bin.display(), src_bin.display(), mode, err));
+            },
+        };
+        std::fs::rename(&tmp_bin, &dst_bin).map_err(|err| {
+            let _ = std::fs::remove_file(&tmp_bin);
+            error!(Link, err, "error replacing `{}` with `{}`: {}", dst_bin.display(), src_bin.display(), err)
+        })?;
+        let _ = std::fs::remove_file(&tmp_bin); // renaming a hardlink over another link to the same file is a no-op
+
+        Event::new("bin-linked").str("crate", name.to_string_lossy()).path("link", &dst_bin).path("target", &src_bin).str("mode", linked).emit(term);
+        if linked != LinkMode::Copy {
+            if verbose { statusln!(term, "Linked", "`{}` to `{}` ({})", dst_bin.display(), src_bin.display(), linked) }
+        } else if !quiet {
+            statusln!(term, "Replaced", "`{}` with `{}`", dst_bin.display(), src_bin.display())
+        }
+        linked_bins.push((dst_bin, src_bin));
+    }
+
+    records.0.insert(name.to_string_lossy().into_owned(), LinkRecords::record(link));
+    if let Err(err) = records.write(dst_bin) {
+        if !quiet { warnln!(term, "Unable to record links in `{}`: {}", LinkRecords::path(dst_bin).display(), err) }
+    }
+
+    linked_bins.sort();
+    Ok(linked_bins)
+}
+
+/// The file names of the bins in `root/bin`.
+fn bin_files(root: &Path) -> Vec<OsString> {
+    root.join("bin").read_dir().map(|dir| dir.filter_map(|e| e.ok()).filter(|e| e.file_type().is_ok_and(|ft| ft.is_file())).map(|e| e.file_name()).collect()).unwrap_or_default()
+}
+
+/// The file names `mode` links the bin `file_name` as: itself, or shims (see [write_shims].)
+fn link_names(mode: LinkMode, file_name: &OsStr) -> Vec<OsString> {
+    if mode != LinkMode::Shim { return vec![file_name.to_os_string()] }
+    let stem = shim_name(file_name);
+    let mut names = vec![stem.to_os_string()];
+    if stem != file_name { // an `.exe`, for Windows
+        let mut cmd = stem.to_os_string();
+        cmd.push(".cmd");
+        names.push(cmd);
+    }
+    names
+}
+
+/// Create `dst` as a link to (or copy of) `src`, returning the kind of link that was actually created.
+/// [LinkMode::Auto] only tries a symlink here: falling back on a copy is left to the caller, which warns first.
+fn link_file(mode: LinkMode, src: &Path, dst: &Path) -> io::Result<LinkMode> {
+    match mode {
+        LinkMode::Auto | LinkMode::Symlink => symlink_file(src, dst).map(|()| LinkMode::Symlink),
+        LinkMode::RelativeSymlink => {
+            let dst_dir = std::fs::canonicalize(dst.parent().unwrap_or(Path::new(".")))?;
+            let src     = std::fs::canonicalize(src)?;
+            let rel     = relative_path(&dst_dir, &src).ok_or_else(|| io::Error::other("no relative path between the cache and bin directories (different drives?)"))?;
+            symlink_file(&rel, dst).map(|()| LinkMode::RelativeSymlink)
+        },
+        LinkMode::Hardlink  => std::fs::hard_link(src, dst).map(|()| LinkMode::Hardlink),
+        LinkMode::Copy      => std::fs::copy(src, dst).map(|_| LinkMode::Copy),
+        LinkMode::Shim      => unreachable!("shims are written by write_shims"),
+    }
+}
+
+fn symlink_file(src: &Path, dst: &Path) -> io::Result<()> {
+    #[cfg(windows)] { std::os::windows::fs::symlink_file(src, dst) }
+    #[cfg(unix)] { std::os::unix::fs::symlink(src, dst) }
+    #[cfg(not(any(windows, unix)))] { let _ = (src, dst); Err(io::Error::other("symlinks not supported on this platform")) }
+}
+
+/// `target` relative to the directory `base`.  Both paths must be absolute & canonical.
+fn relative_path(base: &Path, target: &Path) -> Option<PathBuf> {
+    let base    = base.components().collect::<Vec<_>>();
+    let target  = target.components().collect::<Vec<_>>();
+    let common  = base.iter().zip(target.iter()).take_while(|(b, t)| b == t).count();
+    if common == 0 || matches!(base[..common].last(), Some(Component::Prefix(_))) { return None } // no shared root
+
+    let mut rel = PathBuf::new();
+    for _ in common .. base.len() { rel.push(".."); }
+    for c in target[common..].iter() { rel.push(c); }
+    Some(rel)
+}
+
+/// Write launchers for `src_bin` into `dst_bin`: a POSIX `sh` script named like the bin (minus any `.exe`), plus a
+/// `.cmd` script if it's an `.exe` (built for Windows, whatever the host.)  Like links, each is written under a
+/// temporary name and renamed into place.
+fn write_shims(context: &Context, krate: &OsStr, src_bin: &Path, dst_bin: &Path, shim: &Shim, linked: &mut Vec<(PathBuf, PathBuf)>) -> Result<(), Error> {
+    let Context { verbose, ref term, .. } = *context;
+    let stem = shim_name(src_bin.file_name().unwrap_or_default());
+
+    let mut sh = String::from("#!/bin/sh\n# generated by cargo-local-install, do not edit\n");
+    for (name, value) in shim.env.iter() { writeln!(&mut sh, "{}={}; export {}", name, sh_quote(value), name).unwrap(); }
+    write!(&mut sh, "exec {}", sh_quote(&src_bin.to_string_lossy())).unwrap();
+    for arg in shim.args.iter() { write!(&mut sh, " {}", sh_quote(arg)).unwrap(); }
+    sh.push_str(" \"$@\"\n");
+
+    let mut scripts = vec![(PathBuf::from(stem), sh)];
+
+    if stem != src_bin.file_name().unwrap_or_default() {
+        let mut cmd = String::from("@echo off\r\n:: generated by cargo-local-install, do not edit\r\nsetlocal\r\n");
+        for (name, value) in shim.env.iter() { write!(&mut cmd, "set \"{}={}\"\r\n", name, value.replace('%', "%%")).unwrap(); }
+        write!(&mut cmd, "\"{}\"", src_bin.display()).unwrap();
+        for arg in shim.args.iter() { write!(&mut cmd, " \"{}\"", arg.replace('%', "%%")).unwrap(); }
+        cmd.push_str(" %*\r\nexit /b %ERRORLEVEL%\r\n");
+        let mut name = stem.to_os_string();
+        name.push(".cmd");
+        scripts.push((PathBuf::from(name), cmd));
+    }
+
+    for (name, script) in scripts {
+        let tmp = dst_bin.join(format!(".{}.{}.tmp", name.display(), std::process::id()));
+        let dst = dst_bin.join(name);
+        let write = || -> io::Result<()> {
+            std::fs::write(&tmp, &script)?;
+            #[cfg(unix)] {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o755))?;
+            }
+            std::fs::rename(&tmp, &dst)
+        };
+        write().map_err(|err| {
+            let _ = std::fs::remove_file(&tmp);
+            error!(Link, err, "unable to write shim `{}`: {}", dst.display(), err)
+        })?;
+        Event::new("bin-linked").str("crate", krate.to_string_lossy()).path("link", &dst).path("target", src_bin).str("mode", LinkMode::Shim).emit(term);
+        if verbose { statusln!(term, "Shimmed", "`{}` to `{}`", dst.display(), src_bin.display()) }
+        linked.push((dst, src_bin.to_path_buf()));
+    }
+    Ok(())
+}
+
+/// The name of the `sh` launcher for the bin `file_name`: the same, minus any `.exe`.
+pub(super) fn shim_name(file_name: &OsStr) -> &OsStr {
+    let path = Path::new(file_name);
+    if path.extension() == Some(OsStr::new("exe")) { path.file_stem().unwrap_or(file_name) } else { file_name }
+}
+
+/// Single-quote `s` for POSIX `sh`.
+fn sh_quote(s: &str) -> String { format!("'{}'", s.replace('\'', "'\\''")) }
@@ -0,0 +1,121 @@
+//! Advisory cross-process locks built on lock files, since `#![forbid(unsafe_code)]` rules out `flock` & friends.
+//!
+//! A lock file is created with `create_new` and contains the owner's PID.  While held, a heartbeat thread keeps
+//! touching the file, so a lock whose file hasn't been touched in [STALE_AFTER] is assumed to have been abandoned by a
+//! crashed process and is broken.  PIDs aren't checked for liveness: processes sharing a cache (e.g. CI jobs in separate
+//! containers sharing a home directory) don't necessarily share a PID namespace.  They are checked on release, so a lock
+//! broken while we were stalled (and since taken by someone else) isn't removed out from under its new owner.
+
+use super::*;
+
+use std::fs::OpenOptions;
+use std::io::Write as _;
+use std::sync::{Arc, Condvar};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+
+
+const HEARTBEAT   : Duration = Duration::from_secs(5);
+const STALE_AFTER : Duration = Duration::from_secs(30);
+const POLL        : Duration = Duration::from_millis(250);
+
+pub(super) struct FileLock {
+    path:       PathBuf,
+    contents:   String,
+    stop:       Arc<(Mutex<bool>, Condvar)>,
+    heartbeat:  Option<JoinHandle<()>>,
+}
+
+impl FileLock {
+    /// Acquire `path`, blocking until any other process holding it releases it (or is found to be stale).
+    /// `what` describes the locked resource for "Waiting" status lines.
+    pub(super) fn acquire(term: &Term, path: PathBuf, what: &Path, quiet: bool) -> Result<Self, Error> {
+        let mut waiting = false;
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let contents = format!("{}\n", std::process::id());
+                    let _ = file.write_all(contents.as_bytes());
+                    if waiting && !quiet { statusln!(term, "Locked", "`{}`", what.display()); }
+                    return Ok(Self::start_heartbeat(path, contents));
+                },
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {},
+                Err(err) => return Err(error!(Cache, err, "unable to create lock file `{}`: {}", path.display(), err)),
+            }
+
+            let contents = std::fs::read_to_string(&path).unwrap_or_default();
+            let owner = contents.trim().parse::<u32>().ok();
+            if let Some(modified) = stale_since(&path) {
+                if break_lock(&path, &contents, modified) && !quiet {
+                    warnln!(term, "broke stale lock `{}` (held by process {})", path.display(), owner.map_or_else(|| "?".to_string(), |pid| pid.to_string()));
+                }
+                continue;
+            }
+
+            if !waiting && !quiet {
+                statusln!(term, "Waiting", "for lock on `{}` (held by process {})", what.display(), owner.map_or_else(|| "?".to_string(), |pid| pid.to_string()));
+            }
+            waiting = true;
+            std::thread::sleep(POLL);
+        }
+    }
+
+    fn start_heartbeat(path: PathBuf, contents: String) -> Self {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let heartbeat = {
+            let (path, stop) = (path.clone(), stop.clone());
+            std::thread::spawn(move || {
+                let (stopped, cvar) = &*stop;
+                let mut stopped = stopped.lock().unwrap();
+                while !*stopped {
+                    stopped = cvar.wait_timeout(stopped, HEARTBEAT).unwrap().0;
+                    if let Ok(file) = OpenOptions::new().write(true).open(&path) { let _ = file.set_modified(SystemTime::now()); }
+                }
+            })
+        };
+        Self { path, contents, stop, heartbeat: Some(heartbeat) }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let (stopped, cvar) = &*self.stop;
+        *stopped.lock().unwrap() = true;
+        cvar.notify_all();
+        if let Some(heartbeat) = self.heartbeat.take() { let _ = heartbeat.join(); }
+        remove_if(&self.path, "released", |lock| std::fs::read_to_string(lock).is_ok_and(|c| c == self.contents));
+    }
+}
+
+/// If `path` hasn't been touched in [STALE_AFTER], when it was last touched.
+fn stale_since(path: &Path) -> Option<SystemTime> {
+    let modified = path.metadata().and_then(|m| m.modified()).ok()?; // e.g. released between our open and stat: just retry
+    let age = modified.elapsed().ok()?; // clock skew: just retry
+    if age > STALE_AFTER { Some(modified) } else { None }
+}
+
+/// Remove the stale lock `path`, if it still has the `contents` and `modified` time it was judged stale with.
+fn break_lock(path: &Path, contents: &str, modified: SystemTime) -> bool {
+    remove_if(path, "stale", |broken| std::fs::read_to_string(broken).is_ok_and(|c| c == contents) && broken.metadata().and_then(|m| m.modified()).is_ok_and(|m| m == modified))
+}
+
+/// Remove the lock `path` if it's (still) the lock `same` expects.
+/// Checking `path` in place would race with others removing and recreating it in the meantime, so the lock is first
+/// atomically renamed out of the way (to `<path>.<why>-<pid>`, a name unique to this process), then checked.
+fn remove_if(path: &Path, why: &str, same: impl FnOnce(&Path) -> bool) -> bool {
+    let mut broken = path.as_os_str().to_os_string();
+    broken.push(format!(".{}-{}", why, std::process::id()));
+    let broken = PathBuf::from(broken);
+    if std::fs::rename(path, &broken).is_err() { return false } // someone else already broke it
+
+    let same = same(&broken);
+    if !same {
+        // Oops, that was a fresh lock: put it back, unless yet another lock has been created since
+        if let Err(err) = std::fs::hard_link(&broken, path) {
+            if err.kind() != io::ErrorKind::AlreadyExists { let _ = std::fs::rename(&broken, path); }
+        }
+    }
+    let _ = std::fs::remove_file(&broken);
+    same
+}
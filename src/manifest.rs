@@ -1,344 +1,282 @@
+//! Reading `[package.metadata.local-install]` and `[workspace.metadata.local-install]` from `Cargo.toml`s, into
+//! [Manifest]s of [ToolRequirement]s.
+
 use super::*;
 
-use serde::*;
+#[cfg(              feature = "manifest")   ] mod deserialize;
+#[cfg(any(test, not(feature = "manifest")))] mod mini_toml; // tested against `deserialize` when both are built
 
 use std::borrow::Cow;
-use std::collections::BTreeMap;
-use std::fmt::{self, Formatter};
-use std::ffi::*;
 use std::fs::read_to_string;
-use std::path::*;
-
-
-
-pub(super) fn find_cwd_installs(maybe_dst_bin: Option<PathBuf>) -> Result<Vec<InstallSet>, Error> {
-    let mut path = std::env::current_dir().map_err(|err| error!(err, "unable to determine cwd: {}", err))?;
-    let mut files = Vec::new();
-    loop {
-        path.push("Cargo.toml");
-        if path.exists() {
-            let file = File::from_path(&path)?;
-            let dir = path.parent().unwrap();
-
-            let mut installs = Vec::new();
-            for has_meta in vec![file.toml.workspace, file.toml.package].into_iter().flatten() {
-                for (name, InstallData { package, locked, source, default_features, features }) in has_meta.metadata.local_install.into_iter() {
-                    installs.push({
-                        let name = OsStr::new(package.as_ref().map(|p| p.as_str()).unwrap_or(&name));
-                        let mut flags = match source {
-                            InstallSource::Local { path }                                   => vec![ InstallFlag::new("--path", vec![dir.join(path).into()]) ],
-                            InstallSource::Git { git }                                      => vec![ InstallFlag::new("--git", vec![git.into()]) ],
-                            InstallSource::GitRev { git, rev }                              => vec![ InstallFlag::new("--git", vec![git.into()]), InstallFlag::new("--rev", vec![rev.into()] ) ],
-                            InstallSource::GitBranch { git, branch }                        => vec![ InstallFlag::new("--git", vec![git.into()]), InstallFlag::new("--branch", vec![branch.into()] ) ],
-                            InstallSource::Registry { version, registry: Some(registry) }   => vec![ InstallFlag::new("--version", vec![fix_version(&version).into()]), InstallFlag::new("--registry", vec![registry.into()]) ],
-                            InstallSource::Registry { version, registry: None }             => vec![ InstallFlag::new("--version", vec![fix_version(&version).into()]) ],
-                        };
-                        if locked { flags.push(InstallFlag::new("--locked", vec![])); }
-                        if !default_features { flags.push(InstallFlag::new("--no-default-features", vec![])); }
-                        if !features.is_empty() { flags.push(InstallFlag::new("--features", features.iter().map(|f| f.into()).collect::<Vec<_>>())); }
-                        Install { name: name.into(), flags }
-                    });
-                }
-            }
 
-            // TODO: add flag to search the entire workspace instead of merely the CWD tree?
-            if !installs.is_empty() {
-
-                let file_dst_bin;
-                if let Some(dst_bin) = maybe_dst_bin {
-                    file_dst_bin = dst_bin;
-                } else {
-                    file_dst_bin = file.directory.join("bin");
-                }
-
-                files.push(InstallSet {
-                    bin: file_dst_bin,
-                    src: Some(path.clone()),
-                    installs,
-                });
+
+
+/// Find the `Cargo.toml` in or above `dir`, and the installs its metadata requests.
+pub(super) fn find_installs(dir: &Path, maybe_dst_bin: Option<PathBuf>) -> Result<Vec<InstallSet>, Error> {
+    // TODO: add flag to search the entire workspace instead of merely the CWD tree?
+    let manifest = match Manifest::find(dir)? { Some(m) => m, None => return Ok(Vec::new()) };
+    if manifest.tools.is_empty() { return Ok(Vec::new()) }
+    Ok(vec![InstallSet {
+        bin:        maybe_dst_bin.unwrap_or_else(|| manifest.bin_dir()),
+        installs:   manifest.tools.iter().map(ToolRequirement::install).collect(),
+        src:        Some(manifest.path),
+    }])
+}
+
+
+
+/// A parsed `Cargo.toml`: the tools its `local-install` metadata requests.
+///
+/// ```no_run
+/// # fn a() -> Result<(), cargo_local_install::Error> {
+/// use cargo_local_install::*;
+///
+/// if let Some(manifest) = Manifest::find(".")? {
+///     for tool in manifest.tools() {
+///         println!("{} from {:?} into {}", tool.package(), tool.source(), manifest.bin_dir().display());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    path:       PathBuf,
+    directory:  PathBuf,
+    workspace:  Option<Workspace>,
+    tools:      Vec<ToolRequirement>,
+}
+
+#[derive(Debug, Clone)]
+struct Workspace {
+    members:    Vec<String>,
+    exclude:    Vec<String>,
+}
+
+/// Which table of a `Cargo.toml` a [ToolRequirement] came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataTable {
+    /// `[package.metadata.local-install]`
+    Package,
+    /// `[workspace.metadata.local-install]`
+    Workspace,
+}
+
+/// A single entry of a `local-install` metadata table, e.g. `cargo-web = { version = "0.6", locked = false }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolRequirement {
+    name:               String,
+    table:              MetadataTable,
+    package:            Option<String>,
+    source:             ToolSource,
+    locked:             bool,
+    default_features:   bool,
+    features:           Vec<String>,
+    link_mode:          Option<LinkMode>,
+    toolchain:          Option<String>,
+    shim:               Shim,
+}
+
+/// Where a [ToolRequirement] is installed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ToolSource {
+    /// `version = "..."`, from crates.io or `registry`.  Bare versions like `"0.6"` mean `"^0.6"`, as with `[dependencies]`.
+    Registry    { version: String, registry: Option<String> },
+    /// `path = "..."`, relative to the `Cargo.toml`'s directory.
+    Path        { path: PathBuf },
+    /// `git = "..."`, at the default branch unless `branch` or `rev` is specified.
+    Git         { git: String, branch: Option<String>, rev: Option<String> },
+}
+
+impl Manifest {
+    /// Read and parse the `Cargo.toml` at `path`.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = read_to_string(path).map_err(|err| error!(Manifest, err, "unable to read {}: {}", path.display(), err).with_set(Some(path)))?;
+        Self::parse(&text, path)
+    }
+
+    /// Parse `text` as the contents of the `Cargo.toml` at `path` (which `path` dependencies are relative to.)
+    pub fn parse(text: &str, path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let toml = parse(text, &path).map_err(|err| err.with_set(Some(&path)))?;
+        let mut directory = path.clone();
+        if !directory.pop() { return Err(error!(Manifest, None, "unable to determine containing directory for {}", path.display()).with_set(Some(&path))) }
+
+        let mut tools = Vec::new();
+        for (table, has_meta) in [(MetadataTable::Workspace, toml.workspace.as_ref()), (MetadataTable::Package, toml.package.as_ref())] {
+            for (name, data) in has_meta.into_iter().flat_map(|m| m.metadata.local_install.iter()) {
+                let InstallData { package, locked, default_features, features, link_mode, toolchain, shim, source } = data.clone();
+                let source = match source {
+                    ToolSource::Path { path } => ToolSource::Path { path: directory.join(path) },
+                    other => other,
+                };
+                tools.push(ToolRequirement { name: name.clone(), table, package, source, locked, default_features, features, link_mode, toolchain, shim });
             }
-            break;
         }
-        if !path.pop() || !path.pop() { break }
+
+        let workspace = toml.workspace.map(|ws| Workspace { members: ws.members, exclude: ws.exclude });
+        Ok(Self { path, directory, workspace, tools })
+    }
+
+    /// Find and parse the nearest `Cargo.toml` in or above `dir`, if any.
+    pub fn find(dir: impl AsRef<Path>) -> Result<Option<Self>, Error> {
+        let mut path = dir.as_ref().to_path_buf();
+        loop {
+            path.push("Cargo.toml");
+            if path.exists() { return Self::from_path(&path).map(Some) }
+            if !path.pop() || !path.pop() { return Ok(None) }
+        }
+    }
+
+    /// The path of the `Cargo.toml`.
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// The directory containing the `Cargo.toml`.
+    pub fn directory(&self) -> &Path { &self.directory }
+
+    /// Where `cargo local-install` links this manifest's tools by default: `bin` next to the `Cargo.toml`.
+    pub fn bin_dir(&self) -> PathBuf { self.directory.join("bin") }
+
+    /// `true` if this `Cargo.toml` has a `[workspace]` table.
+    pub fn is_workspace(&self) -> bool { self.workspace.is_some() }
+
+    /// Every tool requested by `[workspace.metadata.local-install]`, then `[package.metadata.local-install]`.
+    pub fn tools(&self) -> &[ToolRequirement] { &self.tools }
+
+    /// Parse the `Cargo.toml` of every `[workspace]` member (per `members` and `exclude`, with `*` wildcards), not
+    /// including this one.  Empty if this isn't a workspace root.
+    pub fn members(&self) -> Result<Vec<Manifest>, Error> {
+        let ws = match self.workspace.as_ref() { Some(ws) => ws, None => return Ok(Vec::new()) };
+        let exclude = ws.exclude.iter().flat_map(|pattern| glob_dirs(&self.directory, pattern)).collect::<Vec<_>>();
+        let mut dirs = ws.members.iter().flat_map(|pattern| glob_dirs(&self.directory, pattern)).filter(|dir| !exclude.contains(dir) && *dir != self.directory).collect::<Vec<_>>();
+        dirs.sort();
+        dirs.dedup();
+        dirs.into_iter().filter(|dir| dir.join("Cargo.toml").exists()).map(|dir| Self::from_path(dir.join("Cargo.toml"))).collect()
     }
-    Ok(files)
 }
 
+impl ToolRequirement {
+    /// The metadata key, e.g. `cargo-web` in `cargo-web = "0.6"`.
+    pub fn name(&self) -> &str { &self.name }
 
+    /// The crate to install: `package = "..."` if specified, otherwise [ToolRequirement::name].
+    pub fn package(&self) -> &str { self.package.as_deref().unwrap_or(&self.name) }
 
-struct File {
-    directory:  PathBuf,
-    //file:     PathBuf,
-    toml:       CargoToml,
+    /// Which table this came from.
+    pub fn table(&self) -> MetadataTable { self.table }
+
+    /// Where to install from.  [ToolSource::Path]s have already been joined with the `Cargo.toml`'s directory.
+    pub fn source(&self) -> &ToolSource { &self.source }
+
+    /// `locked = ...` (default `true`.)
+    pub fn locked(&self) -> bool { self.locked }
+
+    /// `default-features = ...` (default `true`.)
+    pub fn default_features(&self) -> bool { self.default_features }
+
+    /// `features = [...]`
+    pub fn features(&self) -> &[String] { &self.features }
+
+    /// `link-mode = "..."`, if specified.
+    pub fn link_mode(&self) -> Option<LinkMode> { self.link_mode }
+
+    /// `toolchain = "..."` (e.g. `"nightly"`), if specified: installed with `cargo +toolchain install ...`.
+    pub fn toolchain(&self) -> Option<&str> { self.toolchain.as_deref() }
+
+    /// `env = { ... }`, for `shim` launchers.
+    pub fn env(&self) -> &BTreeMap<String, String> { &self.shim.env }
+
+    /// `args = [...]`, for `shim` launchers.
+    pub fn args(&self) -> &[String] { &self.shim.args }
+
+    fn install(&self) -> Install {
+        let mut flags = match &self.source {
+            ToolSource::Path { path }                               => vec![ InstallFlag::new("--path", vec![path.into()]) ],
+            ToolSource::Git { git, branch: Some(branch), .. }       => vec![ InstallFlag::new("--git", vec![git.into()]), InstallFlag::new("--branch", vec![branch.into()]) ],
+            ToolSource::Git { git, branch: None, rev: Some(rev) }   => vec![ InstallFlag::new("--git", vec![git.into()]), InstallFlag::new("--rev", vec![rev.into()]) ],
+            ToolSource::Git { git, branch: None, rev: None }        => vec![ InstallFlag::new("--git", vec![git.into()]) ],
+            ToolSource::Registry { version, registry: Some(registry) } => vec![ InstallFlag::new("--version", vec![fix_version(version).into()]), InstallFlag::new("--registry", vec![registry.into()]) ],
+            ToolSource::Registry { version, registry: None }        => vec![ InstallFlag::new("--version", vec![fix_version(version).into()]) ],
+        };
+        if self.locked { flags.push(InstallFlag::new("--locked", vec![])); }
+        if !self.default_features { flags.push(InstallFlag::new("--no-default-features", vec![])); }
+        if !self.features.is_empty() { flags.push(InstallFlag::new("--features", self.features.iter().map(|f| f.into()).collect::<Vec<_>>())); }
+        Install { name: self.package().into(), flags, link_mode: self.link_mode, toolchain: self.toolchain.clone(), shim: self.shim.clone() }
+    }
 }
 
-#[derive(Default)]
+
+
+#[derive(Debug, Default, PartialEq)]
 struct CargoToml {
     workspace:  Option<HasMetadata>,
     package:    Option<HasMetadata>,
 }
 
-#[derive(Default)]
+#[derive(Debug, Default, PartialEq)]
 struct HasMetadata {
-    metadata: Metadata
+    metadata:   Metadata,
+    members:    Vec<String>, // workspace only
+    exclude:    Vec<String>, // workspace only
 }
 
-#[derive(Default)]
+#[derive(Debug, Default, PartialEq)]
 struct Metadata {
     local_install: BTreeMap<String, InstallData>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 struct InstallData {
     package:    Option<String>,
     locked:     bool,
     // TODO: optional?
     default_features: bool,
     features:   Vec<String>,
-    source:     InstallSource,
+    link_mode:  Option<LinkMode>,
+    toolchain:  Option<String>,
+    shim:       Shim,
+    source:     ToolSource,
 }
 
-enum InstallSource {
-    Registry    { version: String, registry: Option<String> },
-    Local       { path: PathBuf },
-    GitRev      { git: String, rev:    String },
-    GitBranch   { git: String, branch: String },
-    Git         { git: String },
+#[cfg(feature = "manifest")]
+fn parse(text: &str, path: &Path) -> Result<CargoToml, Error> {
+    deserialize::parse(text).map_err(|err| error!(Manifest, err, "unable to parse {}: {}", path.display(), err))
 }
 
-
-
-
-impl<'de> Deserialize<'de> for CargoToml {
-    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
-        struct CargoTomlVisitor;
-        impl<'de> de::Visitor<'de> for CargoTomlVisitor {
-            type Value = CargoToml;
-            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result { formatter.write_str("a workspace or package table") }
-            fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
-                let mut r = Self::Value::default();
-                while let Some(key) = map.next_key()? {
-                    match key {
-                        "package" => {
-                            if r.package.is_some() { return Err(de::Error::duplicate_field("package")) }
-                            r.package = map.next_value()?;
-                        },
-                        "workspace" => {
-                            if r.workspace.is_some() { return Err(de::Error::duplicate_field("workspace")) }
-                            r.workspace = map.next_value()?;
-                        },
-                        _other => {
-                            let _ : de::IgnoredAny = map.next_value()?;
-                        },
-                    }
-                }
-                Ok(r)
-            }
-        }
-        d.deserialize_any(CargoTomlVisitor)
-    }
+#[cfg(not(feature = "manifest"))]
+fn parse(text: &str, path: &Path) -> Result<CargoToml, Error> {
+    mini_toml::parse(text).map_err(|err| error!(Manifest, None, "unable to parse {}: {}", path.display(), err))
 }
 
-impl<'de> Deserialize<'de> for HasMetadata {
-    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
-        struct HasMetadataVisitor;
-        impl<'de> de::Visitor<'de> for HasMetadataVisitor {
-            type Value = HasMetadata;
-            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result { formatter.write_str("a workspace or package table") }
-            fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
-                let mut r = Self::Value::default();
-                let mut one = false;
-                while let Some(key) = map.next_key()? {
-                    match key {
-                        "metadata" => if one {
-                            return Err(de::Error::duplicate_field("metadata"));
-                        } else {
-                            one = true;
-                            r.metadata = map.next_value()?;
-                        },
-                        _other => {
-                            let _ : de::IgnoredAny = map.next_value()?;
-                        },
-                    }
-                }
-                Ok(r)
-            }
-        }
-        d.deserialize_any(HasMetadataVisitor)
-    }
-}
-
-impl<'de> Deserialize<'de> for Metadata {
-    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
-        struct MetadataVisitor;
-        impl<'de> de::Visitor<'de> for MetadataVisitor {
-            type Value = Metadata;
-            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result { formatter.write_str("a metadata table") }
-            fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
-                let mut r = Metadata::default();
-                let mut one = false;
-                while let Some(key) = map.next_key()? {
-                    match key {
-                        "local-install" => if one {
-                            return Err(de::Error::duplicate_field("local-install"));
-                        } else {
-                            one = true;
-                            r.local_install = map.next_value()?;
-                        },
-                        _other => {
-                            let _ : de::IgnoredAny = map.next_value()?;
-                        },
-                    }
-                }
-                Ok(r)
-            }
-        }
-        d.deserialize_any(MetadataVisitor)
-    }
-}
-
-impl<'de> Deserialize<'de> for InstallData {
-    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
-        struct InstallDataVisitor;
-        impl<'de> de::Visitor<'de> for InstallDataVisitor {
-            type Value = InstallData;
-            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result { formatter.write_str("a version string or installation dependency table") }
-            fn visit_str   <E>(self, value: &str  ) -> Result<Self::Value, E> { Ok(InstallData { package: None, locked: true, default_features: true, features: Vec::new(), source: InstallSource::Registry { version: value.into(), registry: None } }) }
-            fn visit_string<E>(self, value: String) -> Result<Self::Value, E> { Ok(InstallData { package: None, locked: true, default_features: true, features: Vec::new(), source: InstallSource::Registry { version: value,        registry: None } }) }
-            fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
-                let mut package     : Option<String> = None;
-                let mut locked      : Option<bool  > = None;
-                let mut default_features    : Option<bool > = None;
-                let mut features    : Option<Vec<String>> = None;
-
-                let mut version     : Option<String> = None;
-                let mut registry    : Option<String> = None;
-                let mut path        : Option<PathBuf> = None;
-                let mut git         : Option<String> = None;
-                let mut rev         : Option<String> = None;
-                let mut branch      : Option<String> = None;
-
-                while let Some(key) = map.next_key()? {
-                    match key {
-                        "package" => {
-                            if package.is_some() { return Err(de::Error::duplicate_field("package")) }
-                            package = Some(map.next_value()?);
-                        },
-                        "locked" => {
-                            if locked.is_some() { return Err(de::Error::duplicate_field("locked")) }
-                            locked = Some(map.next_value()?);
-                        },
-                        "default-features" => {
-                            if default_features.is_some() { return Err(de::Error::duplicate_field("default-features")) }
-                            default_features = Some(map.next_value()?);
-                        },
-                        "features" => {
-                            if features.is_some() { return Err(de::Error::duplicate_field("features")) }
-                            features = Some(map.next_value()?);
-                        },
-                        "version" => {
-                            if version  .is_some() { return Err(de::Error::duplicate_field("version")); }
-                            if path     .is_some() { return Err(de::Error::custom("field `version` conflicts with field `path`")); }
-                            if git      .is_some() { return Err(de::Error::custom("field `version` conflicts with field `git`")); }
-                            if rev      .is_some() { return Err(de::Error::custom("field `version` conflicts with field `rev`")); }
-                            if branch   .is_some() { return Err(de::Error::custom("field `version` conflicts with field `branch`")); }
-                            version = Some(map.next_value()?);
-                        },
-                        "registry" => {
-                            if registry .is_some() { return Err(de::Error::duplicate_field("registry")); }
-                            if path     .is_some() { return Err(de::Error::custom("field `registry` conflicts with field `path`")); }
-                            if git      .is_some() { return Err(de::Error::custom("field `registry` conflicts with field `git`")); }
-                            if rev      .is_some() { return Err(de::Error::custom("field `registry` conflicts with field `rev`")); }
-                            if branch   .is_some() { return Err(de::Error::custom("field `registry` conflicts with field `branch`")); }
-                            registry = Some(map.next_value()?);
-                        }
-                        "path" => {
-                            if path     .is_some() { return Err(de::Error::duplicate_field("path")); }
-                            if version  .is_some() { return Err(de::Error::custom("field `path` conflicts with field `version`")); }
-                            if registry .is_some() { return Err(de::Error::custom("field `path` conflicts with field `registry`")); }
-                            if git      .is_some() { return Err(de::Error::custom("field `path` conflicts with field `git`")); }
-                            if rev      .is_some() { return Err(de::Error::custom("field `path` conflicts with field `rev`")); }
-                            if branch   .is_some() { return Err(de::Error::custom("field `path` conflicts with field `branch`")); }
-                            path = Some(map.next_value()?);
-                        },
-                        "git" => {
-                            if git      .is_some() { return Err(de::Error::duplicate_field("git")); }
-                            if path     .is_some() { return Err(de::Error::custom("field `git` conflicts with field `path`")); }
-                            if version  .is_some() { return Err(de::Error::custom("field `git` conflicts with field `version`")); }
-                            if registry .is_some() { return Err(de::Error::custom("field `git` conflicts with field `registry`")); }
-                            git = Some(map.next_value()?);
-                        },
-                        "rev" => {
-                            if rev      .is_some() { return Err(de::Error::duplicate_field("rev")); }
-                            if path     .is_some() { return Err(de::Error::custom("field `rev` conflicts with field `path`")); }
-                            if version  .is_some() { return Err(de::Error::custom("field `rev` conflicts with field `version`")); }
-                            if registry .is_some() { return Err(de::Error::custom("field `rev` conflicts with field `registry`")); }
-                            if branch   .is_some() { return Err(de::Error::custom("field `rev` conflicts with field `branch`")); }
-                            rev = Some(map.next_value()?);
-                        },
-                        "branch" => {
-                            if branch   .is_some() { return Err(de::Error::duplicate_field("branch")); }
-                            if path     .is_some() { return Err(de::Error::custom("field `branch` conflicts with field `path`")); }
-                            if version  .is_some() { return Err(de::Error::custom("field `branch` conflicts with field `version`")); }
-                            if registry .is_some() { return Err(de::Error::custom("field `branch` conflicts with field `registry`")); }
-                            if rev      .is_some() { return Err(de::Error::custom("field `branch` conflicts with field `rev`")); }
-                            branch = Some(map.next_value()?);
-                        },
-                        other => return Err(de::Error::unknown_field(other, &["package", "locked", "version", "registry", "path", "git", "rev", "branch"])),
-                    }
-                }
-
-                let source = if let Some(version) = version {
-                    InstallSource::Registry { version, registry }
-                } else if let Some(path) = path {
-                    InstallSource::Local { path }
-                } else if let Some(git) = git {
-                    if let Some(branch) = branch {
-                        InstallSource::GitBranch { git, branch }
-                    } else if let Some(rev) = rev {
-                        InstallSource::GitRev { git, rev }
-                    } else {
-                        InstallSource::Git { git }
-                    }
-                } else {
-                    return Err(de::Error::custom("Expected `version`, `path`, or `git`"));
-                };
-
-                Ok(InstallData {
-                    package,
-                    locked: locked.unwrap_or(true),
-                    source,
-                    default_features: default_features.unwrap_or(true),
-                    features: features.unwrap_or_default(),
-                })
-            }
-        }
-        d.deserialize_any(InstallDataVisitor)
+/// The existing directories matching `pattern` (e.g. `"crates/*"`) under `root`.
+fn glob_dirs(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut dirs = vec![root.to_path_buf()];
+    for component in pattern.split(['/', '\\']).filter(|c| !c.is_empty() && *c != ".") {
+        dirs = dirs.into_iter().flat_map(|dir| -> Vec<PathBuf> {
+            if !component.contains('*') { return vec![dir.join(component)] }
+            let mut matches = dir.read_dir().map(|entries| entries.filter_map(|e| e.ok()).filter(|e| e.file_type().is_ok_and(|ft| ft.is_dir())).map(|e| e.path()).filter(|path| {
+                path.file_name().and_then(|n| n.to_str()).is_some_and(|name| wildcard_matches(component, name))
+            }).collect::<Vec<_>>()).unwrap_or_default();
+            matches.sort();
+            matches
+        }).collect();
     }
+    dirs.into_iter().filter(|dir| dir.is_dir()).collect()
 }
 
-
-
-impl File {
-    fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
-        let path = path.as_ref();
-        let text = read_to_string(path).map_err(|err| error!(err, "unable to read {}: {}", path.display(), err))?;
-        Ok(File {
-            toml: toml::from_str(&text).map_err(|err| error!(None, "unable to parse {}: {}", path.display(), err))?,
-            //file: path.into(),
-            directory: {
-                let mut d = path.to_path_buf();
-                if !d.pop() { return Err(error!(None, "unable to determine containing directory for Cargo.toml"))? }
-                d
-            },
-        })
+/// Does `name` match `pattern`, where `*` matches any run of characters?
+pub(super) fn wildcard_matches(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None                    => pattern == name,
+        Some((prefix, rest))    => name.strip_prefix(prefix).is_some_and(|name| (0 ..= name.len()).filter(|i| name.is_char_boundary(*i)).any(|i| wildcard_matches(rest, &name[i..]))),
     }
 }
 
-
-
-fn fix_version(v: &str) -> Cow<OsStr> {
+fn fix_version(v: &str) -> Cow<'_, OsStr> {
     let first = v.chars().next().unwrap_or('\0');
-    if ('0'..='9').contains(&first) {
+    if first.is_ascii_digit() {
         OsString::from(format!("^{}", v)).into()
     } else {
         OsStr::new(v).into()
@@ -0,0 +1,240 @@
+//! `serde` + `toml` parsing of `Cargo.toml`s, with the `manifest` feature.
+
+use super::*;
+
+use serde::*;
+
+use std::fmt::{self, Formatter};
+
+
+
+pub(super) fn parse(text: &str) -> Result<CargoToml, toml::de::Error> { toml::from_str(text) }
+
+
+
+impl<'de> Deserialize<'de> for CargoToml {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        struct CargoTomlVisitor;
+        impl<'de> de::Visitor<'de> for CargoTomlVisitor {
+            type Value = CargoToml;
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result { formatter.write_str("a workspace or package table") }
+            fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut r = Self::Value::default();
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        "package" => {
+                            if r.package.is_some() { return Err(de::Error::duplicate_field("package")) }
+                            r.package = map.next_value()?;
+                        },
+                        "workspace" => {
+                            if r.workspace.is_some() { return Err(de::Error::duplicate_field("workspace")) }
+                            r.workspace = map.next_value()?;
+                        },
+                        _other => {
+                            let _ : de::IgnoredAny = map.next_value()?;
+                        },
+                    }
+                }
+                Ok(r)
+            }
+        }
+        d.deserialize_any(CargoTomlVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for HasMetadata {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        struct HasMetadataVisitor;
+        impl<'de> de::Visitor<'de> for HasMetadataVisitor {
+            type Value = HasMetadata;
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result { formatter.write_str("a workspace or package table") }
+            fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut r = Self::Value::default();
+                let mut one = false;
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        "metadata" => if one {
+                            return Err(de::Error::duplicate_field("metadata"));
+                        } else {
+                            one = true;
+                            r.metadata = map.next_value()?;
+                        },
+                        "members"   => r.members = map.next_value()?,
+                        "exclude"   => r.exclude = map.next_value()?,
+                        _other => {
+                            let _ : de::IgnoredAny = map.next_value()?;
+                        },
+                    }
+                }
+                Ok(r)
+            }
+        }
+        d.deserialize_any(HasMetadataVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for Metadata {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        struct MetadataVisitor;
+        impl<'de> de::Visitor<'de> for MetadataVisitor {
+            type Value = Metadata;
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result { formatter.write_str("a metadata table") }
+            fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut r = Metadata::default();
+                let mut one = false;
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        "local-install" => if one {
+                            return Err(de::Error::duplicate_field("local-install"));
+                        } else {
+                            one = true;
+                            r.local_install = map.next_value()?;
+                        },
+                        _other => {
+                            let _ : de::IgnoredAny = map.next_value()?;
+                        },
+                    }
+                }
+                Ok(r)
+            }
+        }
+        d.deserialize_any(MetadataVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for InstallData {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        struct InstallDataVisitor;
+        impl<'de> de::Visitor<'de> for InstallDataVisitor {
+            type Value = InstallData;
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result { formatter.write_str("a version string or installation dependency table") }
+            fn visit_str   <E>(self, value: &str  ) -> Result<Self::Value, E> { Ok(InstallData { package: None, locked: true, default_features: true, features: Vec::new(), link_mode: None, toolchain: None, shim: Shim::default(), source: ToolSource::Registry { version: value.into(), registry: None } }) }
+            fn visit_string<E>(self, value: String) -> Result<Self::Value, E> { Ok(InstallData { package: None, locked: true, default_features: true, features: Vec::new(), link_mode: None, toolchain: None, shim: Shim::default(), source: ToolSource::Registry { version: value,        registry: None } }) }
+            fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut package     : Option<String> = None;
+                let mut locked      : Option<bool  > = None;
+                let mut default_features    : Option<bool > = None;
+                let mut features    : Option<Vec<String>> = None;
+                let mut link_mode   : Option<LinkMode> = None;
+                let mut toolchain   : Option<String> = None;
+                let mut env         : Option<BTreeMap<String, String>> = None;
+                let mut args        : Option<Vec<String>> = None;
+
+                let mut version     : Option<String> = None;
+                let mut registry    : Option<String> = None;
+                let mut path        : Option<PathBuf> = None;
+                let mut git         : Option<String> = None;
+                let mut rev         : Option<String> = None;
+                let mut branch      : Option<String> = None;
+
+                while let Some(key) = map.next_key()? {
+                    match key {
+                        "package" => {
+                            if package.is_some() { return Err(de::Error::duplicate_field("package")) }
+                            package = Some(map.next_value()?);
+                        },
+                        "locked" => {
+                            if locked.is_some() { return Err(de::Error::duplicate_field("locked")) }
+                            locked = Some(map.next_value()?);
+                        },
+                        "default-features" => {
+                            if default_features.is_some() { return Err(de::Error::duplicate_field("default-features")) }
+                            default_features = Some(map.next_value()?);
+                        },
+                        "features" => {
+                            if features.is_some() { return Err(de::Error::duplicate_field("features")) }
+                            features = Some(map.next_value()?);
+                        },
+                        "link-mode" => {
+                            if link_mode.is_some() { return Err(de::Error::duplicate_field("link-mode")) }
+                            link_mode = Some(map.next_value::<String>()?.parse().map_err(de::Error::custom)?);
+                        },
+                        "toolchain" => {
+                            if toolchain.is_some() { return Err(de::Error::duplicate_field("toolchain")) }
+                            toolchain = Some(map.next_value()?);
+                        },
+                        "env" => {
+                            if env.is_some() { return Err(de::Error::duplicate_field("env")) }
+                            env = Some(map.next_value()?);
+                        },
+                        "args" => {
+                            if args.is_some() { return Err(de::Error::duplicate_field("args")) }
+                            args = Some(map.next_value()?);
+                        },
+                        "version" => {
+                            if version  .is_some() { return Err(de::Error::duplicate_field("version")); }
+                            if path     .is_some() { return Err(de::Error::custom("field `version` conflicts with field `path`")); }
+                            if git      .is_some() { return Err(de::Error::custom("field `version` conflicts with field `git`")); }
+                            if rev      .is_some() { return Err(de::Error::custom("field `version` conflicts with field `rev`")); }
+                            if branch   .is_some() { return Err(de::Error::custom("field `version` conflicts with field `branch`")); }
+                            version = Some(map.next_value()?);
+                        },
+                        "registry" => {
+                            if registry .is_some() { return Err(de::Error::duplicate_field("registry")); }
+                            if path     .is_some() { return Err(de::Error::custom("field `registry` conflicts with field `path`")); }
+                            if git      .is_some() { return Err(de::Error::custom("field `registry` conflicts with field `git`")); }
+                            if rev      .is_some() { return Err(de::Error::custom("field `registry` conflicts with field `rev`")); }
+                            if branch   .is_some() { return Err(de::Error::custom("field `registry` conflicts with field `branch`")); }
+                            registry = Some(map.next_value()?);
+                        }
+                        "path" => {
+                            if path     .is_some() { return Err(de::Error::duplicate_field("path")); }
+                            if version  .is_some() { return Err(de::Error::custom("field `path` conflicts with field `version`")); }
+                            if registry .is_some() { return Err(de::Error::custom("field `path` conflicts with field `registry`")); }
+                            if git      .is_some() { return Err(de::Error::custom("field `path` conflicts with field `git`")); }
+                            if rev      .is_some() { return Err(de::Error::custom("field `path` conflicts with field `rev`")); }
+                            if branch   .is_some() { return Err(de::Error::custom("field `path` conflicts with field `branch`")); }
+                            path = Some(map.next_value()?);
+                        },
+                        "git" => {
+                            if git      .is_some() { return Err(de::Error::duplicate_field("git")); }
+                            if path     .is_some() { return Err(de::Error::custom("field `git` conflicts with field `path`")); }
+                            if version  .is_some() { return Err(de::Error::custom("field `git` conflicts with field `version`")); }
+                            if registry .is_some() { return Err(de::Error::custom("field `git` conflicts with field `registry`")); }
+                            git = Some(map.next_value()?);
+                        },
+                        "rev" => {
+                            if rev      .is_some() { return Err(de::Error::duplicate_field("rev")); }
+                            if path     .is_some() { return Err(de::Error::custom("field `rev` conflicts with field `path`")); }
+                            if version  .is_some() { return Err(de::Error::custom("field `rev` conflicts with field `version`")); }
+                            if registry .is_some() { return Err(de::Error::custom("field `rev` conflicts with field `registry`")); }
+                            if branch   .is_some() { return Err(de::Error::custom("field `rev` conflicts with field `branch`")); }
+                            rev = Some(map.next_value()?);
+                        },
+                        "branch" => {
+                            if branch   .is_some() { return Err(de::Error::duplicate_field("branch")); }
+                            if path     .is_some() { return Err(de::Error::custom("field `branch` conflicts with field `path`")); }
+                            if version  .is_some() { return Err(de::Error::custom("field `branch` conflicts with field `version`")); }
+                            if registry .is_some() { return Err(de::Error::custom("field `branch` conflicts with field `registry`")); }
+                            if rev      .is_some() { return Err(de::Error::custom("field `branch` conflicts with field `rev`")); }
+                            branch = Some(map.next_value()?);
+                        },
+                        other => return Err(de::Error::unknown_field(other, &["package", "locked", "default-features", "features", "link-mode", "toolchain", "env", "args", "version", "registry", "path", "git", "rev", "branch"])),
+                    }
+                }
+
+                let source = if let Some(version) = version {
+                    ToolSource::Registry { version, registry }
+                } else if let Some(path) = path {
+                    ToolSource::Path { path }
+                } else if let Some(git) = git {
+                    ToolSource::Git { git, branch, rev }
+                } else {
+                    return Err(de::Error::custom("Expected `version`, `path`, or `git`"));
+                };
+
+                Ok(InstallData {
+                    package,
+                    locked: locked.unwrap_or(true),
+                    source,
+                    default_features: default_features.unwrap_or(true),
+                    features: features.unwrap_or_default(),
+                    link_mode,
+                    toolchain,
+                    shim: Shim { env: env.unwrap_or_default(), args: args.unwrap_or_default() },
+                })
+            }
+        }
+        d.deserialize_any(InstallDataVisitor)
+    }
+}
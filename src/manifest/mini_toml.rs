@@ -0,0 +1,523 @@
+//! A minimal TOML reader, for builds without the `manifest` feature (and thus without `serde` + `toml`.)  It reads
+//! whole `Cargo.toml`s (tables, arrays of tables, dotted keys, inline tables, arrays, and every kind of string), but only
+//! interprets the parts [Manifest] needs, keeping numbers, dates, etc. as raw text.
+
+use super::*;
+
+use std::str::FromStr;
+
+
+
+type Table = BTreeMap<String, Value>;
+
+enum Value {
+    String(String),
+    Bool(bool),
+    /// Numbers, dates, etc.
+    Other,
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+pub(super) fn parse(text: &str) -> Result<CargoToml, String> {
+    let root = Parser { text, pos: 0 }.document()?;
+    let mut r = CargoToml::default();
+    for (key, value) in root {
+        match key.as_str() {
+            "package"   => r.package    = Some(has_metadata(value, "package")?),
+            "workspace" => r.workspace  = Some(has_metadata(value, "workspace")?),
+            _other      => {},
+        }
+    }
+    Ok(r)
+}
+
+fn has_metadata(value: Value, path: &str) -> Result<HasMetadata, String> {
+    let mut table = as_table(value, path)?;
+    let mut r = HasMetadata::default();
+    if path == "workspace" {
+        r.members = table.remove("members").map_or(Ok(Vec::new()), |v| strings(v, "workspace.members"))?;
+        r.exclude = table.remove("exclude").map_or(Ok(Vec::new()), |v| strings(v, "workspace.exclude"))?;
+    }
+    let metadata = match table.remove("metadata") { Some(v) => as_table(v, &format!("{}.metadata", path))?, None => return Ok(r) };
+    for (name, value) in metadata {
+        if name != "local-install" { continue }
+        for (name, value) in as_table(value, &format!("{}.metadata.local-install", path))? {
+            let key = format!("{}.metadata.local-install.{}", path, name);
+            let data = install_data(value).map_err(|err| format!("{} for key `{}`", err, key))?;
+            r.metadata.local_install.insert(name, data);
+        }
+    }
+    Ok(r)
+}
+
+const FIELDS : &[&str] = &["package", "locked", "default-features", "features", "link-mode", "toolchain", "env", "args", "version", "registry", "path", "git", "rev", "branch"];
+
+const CONFLICTS : &[(&str, &str)] = &[
+    ("version", "path"), ("version", "git"), ("version", "rev"), ("version", "branch"),
+    ("registry", "path"), ("registry", "git"), ("registry", "rev"), ("registry", "branch"),
+    ("path", "git"), ("path", "rev"), ("path", "branch"),
+    ("rev", "branch"),
+];
+
+fn install_data(value: Value) -> Result<InstallData, String> {
+    let mut table = match value {
+        Value::String(version) => return Ok(InstallData { package: None, locked: true, default_features: true, features: Vec::new(), link_mode: None, toolchain: None, shim: Shim::default(), source: ToolSource::Registry { version, registry: None } }),
+        Value::Table(table) => table,
+        _other => return Err(String::from("invalid type, expected a version string or installation dependency table")),
+    };
+    if let Some(other) = table.keys().find(|key| !FIELDS.contains(&key.as_str())) {
+        return Err(format!("unknown field `{}`, expected one of {}", other, FIELDS.iter().map(|f| format!("`{}`", f)).collect::<Vec<_>>().join(", ")));
+    }
+    if let Some((a, b)) = CONFLICTS.iter().find(|(a, b)| table.contains_key(*a) && table.contains_key(*b)) {
+        return Err(format!("field `{}` conflicts with field `{}`", a, b));
+    }
+
+    let mut string = |key: &str| table.remove(key).map(|v| match v { Value::String(s) => Ok(s), _other => Err(format!("invalid type for `{}`, expected a string", key)) }).transpose();
+    let package     = string("package")?;
+    let link_mode   = string("link-mode")?.map(|m| LinkMode::from_str(&m)).transpose()?;
+    let toolchain   = string("toolchain")?;
+    let version     = string("version")?;
+    let registry    = string("registry")?;
+    let path        = string("path")?;
+    let git         = string("git")?;
+    let rev         = string("rev")?;
+    let branch      = string("branch")?;
+
+    let mut boolean = |key: &str| table.remove(key).map(|v| match v { Value::Bool(b) => Ok(b), _other => Err(format!("invalid type for `{}`, expected a boolean", key)) }).transpose();
+    let locked              = boolean("locked")?;
+    let default_features    = boolean("default-features")?;
+
+    let features    = table.remove("features").map(|v| strings(v, "features")).transpose()?;
+    let args        = table.remove("args").map(|v| strings(v, "args")).transpose()?;
+    let env         = table.remove("env").map(|v| as_table(v, "env")?.into_iter().map(|(name, v)| match v {
+        Value::String(s) => Ok((name, s)),
+        _other => Err(format!("invalid type for `env.{}`, expected a string", name)),
+    }).collect::<Result<BTreeMap<_, _>, _>>()).transpose()?;
+
+    let source = if let Some(version) = version {
+        ToolSource::Registry { version, registry }
+    } else if let Some(path) = path {
+        ToolSource::Path { path: path.into() }
+    } else if let Some(git) = git {
+        ToolSource::Git { git, branch, rev }
+    } else {
+        return Err(String::from("Expected `version`, `path`, or `git`"));
+    };
+
+    Ok(InstallData {
+        package,
+        locked: locked.unwrap_or(true),
+        source,
+        default_features: default_features.unwrap_or(true),
+        features: features.unwrap_or_default(),
+        link_mode,
+        toolchain,
+        shim: Shim { env: env.unwrap_or_default(), args: args.unwrap_or_default() },
+    })
+}
+
+fn as_table(value: Value, path: &str) -> Result<Table, String> {
+    match value {
+        Value::Table(table) => Ok(table),
+        _other              => Err(format!("invalid type for `{}`, expected a table", path)),
+    }
+}
+
+fn strings(value: Value, path: &str) -> Result<Vec<String>, String> {
+    match value {
+        Value::Array(values) => values.into_iter().map(|v| match v { Value::String(s) => Ok(s), _other => Err(format!("invalid type for `{}`, expected an array of strings", path)) }).collect(),
+        _other => Err(format!("invalid type for `{}`, expected an array of strings", path)),
+    }
+}
+
+
+
+struct Parser<'a> {
+    text:   &'a str,
+    pos:    usize,
+}
+
+impl Parser<'_> {
+    fn document(&mut self) -> Result<Table, String> {
+        let mut root = Table::new();
+        let mut current = Vec::<String>::new();
+        loop {
+            self.skip_blank();
+            if self.peek().is_none() { return Ok(root) }
+            if self.eat("[[") {
+                let path = self.key()?;
+                self.skip_ws();
+                if !self.eat("]]") { return self.err("expected `]]`") }
+                let (last, parent) = path.split_last().unwrap();
+                let parent = self.table_mut(&mut root, parent)?;
+                match parent.entry(last.clone()).or_insert_with(|| Value::Array(Vec::new())) {
+                    Value::Array(tables) => tables.push(Value::Table(Table::new())),
+                    _other => return self.err(&format!("key `{}` isn't an array of tables", last)),
+                }
+                self.end_of_line()?;
+                current = path;
+            } else if self.eat("[") {
+                let path = self.key()?;
+                self.skip_ws();
+                if !self.eat("]") { return self.err("expected `]`") }
+                self.table_mut(&mut root, &path)?;
+                self.end_of_line()?;
+                current = path;
+            } else {
+                let (path, value) = self.key_value()?;
+                let table = self.table_mut(&mut root, &current)?;
+                self.insert(table, path, value)?;
+                self.end_of_line()?;
+            }
+        }
+    }
+
+    /// `key = value`
+    fn key_value(&mut self) -> Result<(Vec<String>, Value), String> {
+        let path = self.key()?;
+        self.skip_ws();
+        if !self.eat("=") { return self.err("expected `=`") }
+        self.skip_ws();
+        Ok((path, self.value()?))
+    }
+
+    /// A (possibly dotted) key, e.g. `package.metadata."local-install"`.
+    fn key(&mut self) -> Result<Vec<String>, String> {
+        let mut path = Vec::new();
+        loop {
+            self.skip_ws();
+            path.push(match self.peek() {
+                Some('"')   => self.basic_string()?,
+                Some('\'')  => self.literal_string()?,
+                _other      => {
+                    let rest = &self.text[self.pos..];
+                    let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_')).unwrap_or(rest.len());
+                    if len == 0 { return self.err("expected a key") }
+                    self.pos += len;
+                    rest[..len].to_string()
+                },
+            });
+            self.skip_ws();
+            if !self.eat(".") { return Ok(path) }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') | Some('\'') => Ok(Value::String(self.string()?)),
+            Some('[') => {
+                self.pos += 1;
+                let mut values = Vec::new();
+                loop {
+                    self.skip_blank();
+                    if self.eat("]") { return Ok(Value::Array(values)) }
+                    values.push(self.value()?);
+                    self.skip_blank();
+                    if !self.eat(",") {
+                        if self.eat("]") { return Ok(Value::Array(values)) }
+                        return self.err("expected `,` or `]`");
+                    }
+                }
+            },
+            Some('{') => {
+                self.pos += 1;
+                let mut table = Table::new();
+                self.skip_blank();
+                if self.eat("}") { return Ok(Value::Table(table)) }
+                loop {
+                    let (path, value) = self.key_value()?;
+                    self.insert(&mut table, path, value)?;
+                    self.skip_blank();
+                    if self.eat("}") { return Ok(Value::Table(table)) }
+                    if !self.eat(",") { return self.err("expected `,` or `}`") }
+                    self.skip_blank();
+                }
+            },
+            _other => {
+                let rest = &self.text[self.pos..];
+                let len = rest.find([',', ']', '}', '#', '\r', '\n']).unwrap_or(rest.len());
+                let token = rest[..len].trim_end();
+                self.pos += len;
+                match token {
+                    ""      => self.err("expected a value"),
+                    "true"  => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    _other  => Ok(Value::Other),
+                }
+            },
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        if self.eat("\"\"\"") {
+            self.eat("\r");
+            self.eat("\n");
+            self.escaped_until("\"\"\"", true)
+        } else if self.eat("'''") {
+            self.eat("\r");
+            self.eat("\n");
+            self.raw_until("'''")
+        } else if self.peek() == Some('"') {
+            self.basic_string()
+        } else {
+            self.literal_string()
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        self.escaped_until("\"", false)
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let rest = &self.text[self.pos..];
+        match rest.find(['\'', '\n']) {
+            Some(len) if rest[len..].starts_with('\'') => { self.pos += len + 1; Ok(rest[..len].to_string()) },
+            Some(len)   => { self.pos += len; self.err("newline in string") },
+            None        => self.err("unterminated string"),
+        }
+    }
+
+    fn raw_until(&mut self, end: &str) -> Result<String, String> {
+        let rest = &self.text[self.pos..];
+        let len = match rest.find(end) { Some(len) => len, None => return self.err("unterminated string") };
+        self.pos += len + end.len();
+        Ok(rest[..len].to_string())
+    }
+
+    fn escaped_until(&mut self, end: &str, multiline: bool) -> Result<String, String> {
+        let mut s = String::new();
+        loop {
+            if self.eat(end) { return Ok(s) }
+            match self.bump() {
+                None                    => return self.err("unterminated string"),
+                Some('\n') if !multiline => { self.pos -= 1; return self.err("newline in string") },
+                Some('\\') => match self.bump() {
+                    Some('b')   => s.push('\u{0008}'),
+                    Some('t')   => s.push('\t'),
+                    Some('n')   => s.push('\n'),
+                    Some('f')   => s.push('\u{000C}'),
+                    Some('r')   => s.push('\r'),
+                    Some('"')   => s.push('"'),
+                    Some('\\')  => s.push('\\'),
+                    Some(u @ 'u') | Some(u @ 'U') => {
+                        let len = if u == 'u' { 4 } else { 8 };
+                        let hex = self.text.get(self.pos .. self.pos + len).unwrap_or("");
+                        match u32::from_str_radix(hex, 16).ok().and_then(char::from_u32) {
+                            Some(c) => { s.push(c); self.pos += len; },
+                            None    => return self.err("invalid unicode escape"),
+                        }
+                    },
+                    Some(c) if multiline && c.is_whitespace() => {
+                        // line ending backslash: trim the newline and any leading whitespace on the next line
+                        let rest = &self.text[self.pos..];
+                        self.pos += rest.len() - rest.trim_start().len();
+                    },
+                    _other => return self.err("invalid escape"),
+                },
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    /// Get the table at `path` under `root`, creating it if necessary, or the last table of an array of tables.
+    fn table_mut<'t>(&self, mut table: &'t mut Table, path: &[String]) -> Result<&'t mut Table, String> {
+        for key in path {
+            table = match table.entry(key.clone()).or_insert_with(|| Value::Table(Table::new())) {
+                Value::Table(t) => t,
+                Value::Array(a) => match a.last_mut() { Some(Value::Table(t)) => t, _other => return self.err(&format!("key `{}` isn't a table", key)) },
+                _other => return self.err(&format!("key `{}` isn't a table", key)),
+            };
+        }
+        Ok(table)
+    }
+
+    fn insert(&self, table: &mut Table, path: Vec<String>, value: Value) -> Result<(), String> {
+        let (last, parent) = path.split_last().unwrap();
+        let table = self.table_mut(table, parent)?;
+        if table.contains_key(last) { return self.err(&format!("duplicate key `{}`", last)) }
+        table.insert(last.clone(), value);
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<char> { self.text[self.pos..].chars().next() }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, s: &str) -> bool {
+        let eaten = self.text[self.pos..].starts_with(s);
+        if eaten { self.pos += s.len(); }
+        eaten
+    }
+
+    /// Skip spaces and tabs.
+    fn skip_ws(&mut self) { while self.eat(" ") || self.eat("\t") {} }
+
+    /// Skip whitespace, newlines, and comments.
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_ws();
+            if self.eat("#") {
+                let rest = &self.text[self.pos..];
+                self.pos += rest.find('\n').unwrap_or(rest.len());
+            } else if !(self.eat("\n") || self.eat("\r\n")) {
+                return;
+            }
+        }
+    }
+
+    /// Skip trailing whitespace and comments, then expect a newline or the end of the file.
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_ws();
+        if self.eat("#") {
+            let rest = &self.text[self.pos..];
+            self.pos += rest.find('\n').unwrap_or(rest.len());
+        }
+        if self.peek().is_none() || self.eat("\n") || self.eat("\r\n") { Ok(()) } else { self.err("expected a newline") }
+    }
+
+    fn err<T>(&self, message: &str) -> Result<T, String> {
+        let line = self.text[..self.pos].matches('\n').count() + 1;
+        Err(format!("{} at line {}", message, line))
+    }
+}
+
+
+
+#[cfg(test)] mod tests {
+    use super::*;
+
+    const CARGO_TOML : &str = r#"# a comment
+[package]
+name = "example" # trailing comment
+version = "0.1.0"
+description = """
+Multi-line, # not a comment
+"and quoted" \
+    continued"""
+readme = '''
+# Not a comment either
+'''
+authors = [
+    "a", # comment
+    # a whole line of comment
+    "b",
+]
+"quoted.key" = 1
+release = 1979-05-27T07:32:00Z
+
+[[bin]]
+name = "a"
+path = "src/a.rs"
+
+[[bin]]
+name = "b"
+
+[package.metadata."local-install"]
+tool = "1"
+'quoted' = { version = "2", features = ["a", "b"], env = { A = 'lit # not a comment' }, args = ["--x"] }
+"with.dot" = { path = "p", link-mode = "copy", locked = false }
+
+[workspace]
+members = ["a", "b/*"] # trailing comment
+exclude = [
+    "c", # trailing comment
+]
+"#;
+
+    const DOTTED : &str = r#"
+[package]
+metadata.local-install.tool = { git = "https://example.com/tool", branch = "main" }
+metadata.local-install."other".version = "1"
+metadata.local-install.other.default-features = false
+"#;
+
+    /// `[package.metadata.local-install]`, by name.
+    fn tools(text: &str) -> BTreeMap<String, InstallData> { parse(text).unwrap().package.unwrap().metadata.local_install }
+
+    #[test] fn reads_cargo_tomls() {
+        let tools = tools(CARGO_TOML);
+        assert_eq!(tools.keys().collect::<Vec<_>>(), ["quoted", "tool", "with.dot"]);
+        assert_eq!(tools["tool"].source, ToolSource::Registry { version: "1".into(), registry: None });
+        assert_eq!(tools["quoted"].features, ["a", "b"]);
+        assert_eq!(tools["quoted"].shim.env["A"], "lit # not a comment");
+        assert_eq!(tools["quoted"].shim.args, ["--x"]);
+        assert_eq!(tools["with.dot"].source, ToolSource::Path { path: "p".into() });
+        assert_eq!(tools["with.dot"].link_mode, Some(LinkMode::Copy));
+        assert!(!tools["with.dot"].locked);
+
+        let workspace = parse(CARGO_TOML).unwrap().workspace.unwrap();
+        assert_eq!(workspace.members, ["a", "b/*"]);
+        assert_eq!(workspace.exclude, ["c"]);
+    }
+
+    #[test] fn crlf() {
+        assert_eq!(parse(&CARGO_TOML.replace('\n', "\r\n")).unwrap(), parse(CARGO_TOML).unwrap());
+        assert_eq!(parse(&DOTTED.replace('\n', "\r\n")).unwrap(), parse(DOTTED).unwrap());
+    }
+
+    #[test] fn dotted_keys() {
+        let tools = tools(DOTTED);
+        assert_eq!(tools["tool"].source, ToolSource::Git { git: "https://example.com/tool".into(), branch: Some("main".into()), rev: None });
+        assert_eq!(tools["other"].source, ToolSource::Registry { version: "1".into(), registry: None });
+        assert!(!tools["other"].default_features);
+    }
+
+    #[test] fn escapes() {
+        let mut parser = Parser { text: r#""\t\"\u00e9\U0001F600""#, pos: 0 };
+        assert_eq!(parser.string().unwrap(), "\t\"\u{e9}\u{1F600}");
+        let mut parser = Parser { text: "\"\"\"\r\na\\\r\n   b\"\"\"", pos: 0 };
+        assert_eq!(parser.string().unwrap(), "ab");
+    }
+
+    const ERRORS : &[(&str, &str)] = &[
+        ("[package\n",                                                      "expected `]` at line 1"),
+        ("[package]\nname = \"x\n",                                         "newline in string at line 2"),
+        ("[package]\nname = 'x",                                            "unterminated string at line 2"),
+        ("[package]\nname = 'x\n'\n",                                       "newline in string at line 2"),
+        ("[package]\nname = \"\\q\"",                                       "invalid escape at line 2"),
+        ("[package]\nname =\n",                                             "expected a value at line 2"),
+        ("[package]\nname\n",                                               "expected `=` at line 2"),
+        ("[package]\nname = \"a\" \"b\"\n",                                 "expected a newline at line 2"),
+        ("[package.metadata.local-install]\na = \"1\"\na = \"2\"\n",         "duplicate key `a` at line 3"),
+        ("[package]\n[[package]]\n",                                        "key `package` isn't an array of tables at line 2"),
+        ("package = 1\n[package.metadata]\n",                               "key `package` isn't a table at line 2"),
+        ("[package]\nauthors = [\"a\" \"b\"]\n",                            "expected `,` or `]` at line 2"),
+        ("[package.metadata.local-install]\ntool = 1\n",                    "invalid type, expected a version string or installation dependency table for key `package.metadata.local-install.tool`"),
+        ("[package.metadata.local-install]\ntool = { vers = \"1\" }\n",     "unknown field `vers`, expected one of `package`, `locked`, `default-features`, `features`, `link-mode`, `toolchain`, `env`, `args`, `version`, `registry`, `path`, `git`, `rev`, `branch` for key `package.metadata.local-install.tool`"),
+        ("[package.metadata.local-install]\ntool = { version = \"1\", path = \"p\" }\n", "field `version` conflicts with field `path` for key `package.metadata.local-install.tool`"),
+        ("[package.metadata.local-install]\ntool = { features = \"a\", version = \"1\" }\n", "invalid type for `features`, expected an array of strings for key `package.metadata.local-install.tool`"),
+        ("[package.metadata.local-install]\ntool = { locked = \"no\", version = \"1\" }\n", "invalid type for `locked`, expected a boolean for key `package.metadata.local-install.tool`"),
+        ("[package.metadata.local-install]\ntool = { features = [] }\n",   "Expected `version`, `path`, or `git` for key `package.metadata.local-install.tool`"),
+        ("[workspace]\nmembers = \"a\"\n",                                  "invalid type for `workspace.members`, expected an array of strings"),
+    ];
+
+    #[test] fn errors() {
+        for (text, expected) in ERRORS {
+            assert_eq!(parse(text).unwrap_err(), *expected, "{:?}", text);
+        }
+    }
+
+    /// Both readers agree on everything above, and on the `test/` workspace.
+    #[cfg(feature = "manifest")]
+    #[test] fn matches_deserialize() {
+        let test = Path::new(env!("CARGO_MANIFEST_DIR")).join("test");
+        let mut paths = test.read_dir().unwrap().map(|e| e.unwrap().path().join("Cargo.toml")).filter(|path| path.exists()).collect::<Vec<_>>();
+        paths.push(test.join("Cargo.toml"));
+        assert!(paths.len() > 1);
+        let fixtures = paths.iter().map(|path| std::fs::read_to_string(path).unwrap()).collect::<Vec<_>>();
+
+        for text in fixtures.iter().map(String::as_str).chain([CARGO_TOML, DOTTED]) {
+            assert_eq!(parse(text).unwrap(), super::super::deserialize::parse(text).unwrap(), "{}", text);
+        }
+        for (text, _) in ERRORS.iter().filter(|(_, err)| !err.starts_with("duplicate key")) { // toml 0.5 lets the last one win
+            assert!(super::super::deserialize::parse(text).is_err(), "{:?}", text);
+        }
+    }
+}
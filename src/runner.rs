@@ -0,0 +1,49 @@
+//! [CargoRunner]: how `cargo` (and `rustc -vV`) actually get run.  [ProcessRunner] by default, swappable via
+//! `InstallOptions::runner` (with the `test-runner` feature) so tests can fake `cargo install` without network access
+//! or real builds.
+
+use super::*;
+
+use std::io::Read;
+
+
+
+/// Runs `cargo` (and `rustc -vV`) commands.  Not a stable API: only exposed for `tests/`, via the `test-runner` feature.
+#[doc(hidden)]
+pub trait CargoRunner: Send + Sync {
+    /// Start `cmd`, a `cargo` or `rustc` invocation (e.g. `rustc -vV` or `cargo install ... --root X -- crate`), honoring
+    /// whichever of its stdout and stderr were set to [Stdio::piped].
+    fn spawn(&self, cmd: &mut Command) -> io::Result<CargoProcess>;
+}
+
+/// A started [CargoRunner] command.
+#[doc(hidden)]
+pub struct CargoProcess {
+    pub stdout: Option<Box<dyn Read + Send>>,
+    pub stderr: Option<Box<dyn Read + Send>>,
+    /// Wait for the command to finish, returning its exit code (`None` if it was killed by a signal.)
+    pub wait:   Box<dyn FnOnce() -> io::Result<Option<i32>> + Send>,
+}
+
+/// Actually spawns processes.
+pub(super) struct ProcessRunner;
+
+impl CargoRunner for ProcessRunner {
+    fn spawn(&self, cmd: &mut Command) -> io::Result<CargoProcess> {
+        let mut child = cmd.spawn()?;
+        Ok(CargoProcess {
+            stdout: child.stdout.take().map(|o| Box::new(o) as Box<dyn Read + Send>),
+            stderr: child.stderr.take().map(|e| Box::new(e) as Box<dyn Read + Send>),
+            wait:   Box::new(move || child.wait().map(|status| status.code())),
+        })
+    }
+}
+
+/// Run `cmd` to completion, returning its stdout (if piped) and exit code.
+pub(super) fn output(runner: &dyn CargoRunner, cmd: &mut Command) -> io::Result<(String, Option<i32>)> {
+    let process = runner.spawn(cmd)?;
+    let mut stdout = String::new();
+    if let Some(mut o) = process.stdout { o.read_to_string(&mut stdout)?; }
+    let code = (process.wait)()?;
+    Ok((stdout, code))
+}
@@ -0,0 +1,201 @@
+//! The end-of-run summary: one row per crate, printed as a table (unless `--quiet`) and emitted as `tool-summary` events,
+//! then returned to library callers as an [InstallReport].
+
+use super::*;
+
+use std::time::Duration;
+
+
+
+/// How a [Tool] turned out, short of failing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Built (or rebuilt) by `cargo install`.
+    Built,
+    /// Already in the cache, just (re)linked.
+    Cached,
+    /// Up to date, or `--dry-run`.
+    Skipped,
+}
+
+impl Outcome {
+    /// `"built"`, `"cached"`, or `"skipped"`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Outcome::Built      => "built",
+            Outcome::Cached     => "cached",
+            Outcome::Skipped    => "skipped",
+        }
+    }
+}
+
+/// One row of the summary.
+pub(super) struct ToolSummary {
+    pub name:       OsString,
+    /// e.g. "^0.6", "latest", "path", or "git"
+    pub requested:  String,
+    /// The version cargo actually installed, per the cache entry's `.crates.toml`.
+    pub resolved:   Option<String>,
+    /// Where the crate's bins were (or would have been) linked.
+    pub bin:        PathBuf,
+    /// How long the build took, including waiting on the cache entry's lock.
+    pub duration:   Option<Duration>,
+    pub result:     Result<Outcome, Error>,
+    pub root:       PathBuf,
+    pub set:        Option<PathBuf>,
+    /// `(link, target)` pairs
+    pub links:      Vec<(PathBuf, PathBuf)>,
+}
+
+impl ToolSummary {
+    pub(super) fn new(install_flags: &[InstallFlag], link: &Link, duration: Option<Duration>, result: Result<Outcome, Error>, links: Vec<(PathBuf, PathBuf)>) -> Self {
+        let flag = |name: &str| install_flags.iter().find(|f| f.flag == name);
+        let requested = if let Some(version) = flag("--version") {
+            version.args.first().map_or_else(String::new, |v| v.to_string_lossy().into_owned())
+        } else if flag("--path").is_some() {
+            String::from("path")
+        } else if flag("--git").is_some() {
+            String::from("git")
+        } else {
+            String::from("latest")
+        };
+        let resolved = if result.is_ok() { resolved_version(&link.root, &link.name) } else { None };
+        Self { name: link.name.clone(), requested, resolved, bin: link.dst_bin.clone(), duration, result, root: link.root.clone(), set: link.set.clone(), links }
+    }
+
+    fn status(&self) -> &'static str { self.result.as_ref().map_or("failed", |o| o.as_str()) }
+}
+
+/// Read which version of `krate` cargo installed into `root`, from lines like `"name 1.2.3 (source)" = ["bin"]`.
+fn resolved_version(root: &Path, krate: &OsStr) -> Option<String> {
+    let text = std::fs::read_to_string(root.join(".crates.toml")).ok()?;
+    let krate = krate.to_string_lossy();
+    text.lines().find_map(|line| {
+        let mut words = line.trim_start_matches('"').split(' ');
+        if words.next()? != krate { return None }
+        Some(words.next()?.to_string())
+    })
+}
+
+/// Emit a `tool-summary` event for every row, and print them as a table unless `quiet` (failures are always printed).
+pub(super) fn report(term: &Term, tools: &[ToolSummary], quiet: bool) {
+    for tool in tools.iter() {
+        let mut e = Event::new("tool-summary")
+            .str("crate", tool.name.to_string_lossy())
+            .str("requested", &tool.requested)
+            .path("bin", &tool.bin)
+            .str("status", tool.status());
+        e = match tool.resolved.as_ref() { Some(v) => e.str("resolved", v), None => e.raw("resolved", "null") };
+        e = match tool.duration { Some(d) => e.num("duration", d.as_secs_f64()), None => e.raw("duration", "null") };
+        if let Err(err) = tool.result.as_ref() { e = e.str("error", err); }
+        e.emit(term);
+    }
+
+    let failed = tools.iter().filter(|t| t.result.is_err()).count();
+    if tools.is_empty() || (quiet && failed == 0) { return }
+
+    let count = |o: Outcome| tools.iter().filter(|t| t.result.as_ref().is_ok_and(|r| *r == o)).count();
+    statusln!(term, "Summary", "{} built, {} cached, {} skipped, {} failed", count(Outcome::Built), count(Outcome::Cached), count(Outcome::Skipped), failed);
+
+    let rows = tools.iter().filter(|t| !quiet || t.result.is_err()).map(|t| [
+        t.name.to_string_lossy().into_owned(),
+        t.requested.clone(),
+        t.resolved.clone().unwrap_or_else(|| String::from("-")),
+        t.status().to_string(),
+        t.duration.map_or_else(|| String::from("-"), |d| format!("{:.2}s", d.as_secs_f32())),
+        t.bin.display().to_string(),
+    ]).collect::<Vec<_>>();
+    let header = ["crate", "requested", "resolved", "status", "time", "bin"].map(String::from);
+    let mut widths = [0; 6];
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        for (w, col) in widths.iter_mut().zip(row.iter()) { *w = (*w).max(col.len()); }
+    }
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        let mut line = format!("{: >12}", "");
+        for (i, (w, col)) in widths.iter().zip(row.iter()).enumerate() {
+            if i + 1 == row.len() { write!(&mut line, " {}", col).unwrap(); } else { write!(&mut line, " {: <w$} ", col, w = w).unwrap(); }
+        }
+        term.line(line.trim_end());
+    }
+    for tool in tools.iter() {
+        if let Err(err) = tool.result.as_ref() { errorln!(term, "`{}`: {}", tool.name.to_string_lossy(), err); }
+    }
+}
+
+
+
+/// What [Installer::install] installed, and where.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct InstallReport {
+    /// One per bin directory (typically one per `Cargo.toml` with `local-install` metadata.)
+    pub sets: Vec<InstallSetReport>,
+}
+
+/// The tools installed into a single bin directory.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct InstallSetReport {
+    /// The directory bins were linked into.
+    pub bin_dir:    PathBuf,
+    /// The `Cargo.toml` whose metadata requested these tools, if any.
+    pub manifest:   Option<PathBuf>,
+    pub tools:      Vec<ToolReport>,
+}
+
+/// A single installed tool.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ToolReport {
+    /// The crate name.
+    pub name:               String,
+    /// The `~/.cargo/local-install/crates/<hash>` directory it was built into.
+    pub cache_dir:          PathBuf,
+    /// The requested version (e.g. `"^0.6"`), or `"latest"`, `"path"`, or `"git"`.
+    pub requested_version:  String,
+    /// The version cargo actually installed, if known.
+    pub resolved_version:   Option<String>,
+    pub outcome:            Outcome,
+    /// `true` unless `cargo install` actually (re)built the tool.
+    pub cache_hit:          bool,
+    /// How long building took, if it was built (or checked) by this run.
+    pub build_duration:     Option<Duration>,
+    /// Every `(link path, target path)` in [InstallSetReport::bin_dir], e.g. `("bin/tool", "~/.cargo/local-install/crates/<hash>/bin/tool")`.
+    /// Empty for `--dry-run`s.
+    pub links:              Vec<(PathBuf, PathBuf)>,
+}
+
+impl InstallReport {
+    /// Group successful `tools` by bin directory.
+    pub(super) fn new(tools: Vec<ToolSummary>) -> Self {
+        let mut report = Self::default();
+        for tool in tools {
+            let outcome = match tool.result { Ok(outcome) => outcome, Err(_) => continue };
+            let set = match report.sets.iter().position(|set| set.bin_dir == tool.bin) {
+                Some(i) => &mut report.sets[i],
+                None => { report.sets.push(InstallSetReport { bin_dir: tool.bin.clone(), manifest: tool.set.clone(), tools: Vec::new() }); report.sets.last_mut().unwrap() },
+            };
+            set.tools.push(ToolReport {
+                name:               tool.name.to_string_lossy().into_owned(),
+                cache_dir:          tool.root,
+                requested_version:  tool.requested,
+                resolved_version:   tool.resolved,
+                outcome,
+                cache_hit:          outcome != Outcome::Built,
+                build_duration:     tool.duration,
+                links:              tool.links,
+            });
+        }
+        report
+    }
+
+    /// Every tool, across all [InstallReport::sets].
+    pub fn tools(&self) -> impl Iterator<Item = &ToolReport> { self.sets.iter().flat_map(|set| set.tools.iter()) }
+
+    /// The link to the bin named `name` (without any `.exe`), if any.
+    pub fn bin(&self, name: &str) -> Option<&Path> {
+        self.tools().flat_map(|tool| tool.links.iter()).map(|(link, _)| link.as_path()).find(|link| {
+            link.file_stem().is_some_and(|stem| stem == name) && link.extension().is_none_or(|ext| ext == "exe")
+        })
+    }
+}
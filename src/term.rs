@@ -0,0 +1,78 @@
+//! Our own stderr output (see `errorln!`, `warnln!`, `statusln!`), colored only when appropriate, or sent to a
+//! library caller's sink instead (see [InstallOptions::log_to]), uncolored.
+//!
+//! Coloring is decided by `--color auto|always|never`, falling back on `CARGO_TERM_COLOR`, then `auto`: colored if
+//! stderr is a terminal (other than `TERM=dumb`) and `NO_COLOR` isn't set.  Child cargos are passed the same decision.
+
+use super::*;
+
+use std::io::IsTerminal;
+use std::sync::Arc;
+
+
+
+/// Receives every line of human readable output, instead of stderr.
+pub(super) type Sink = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Where an install's output goes, and how: passed down (via [Context]) to everything that reports anything, so
+/// concurrent [Installer]s never share output settings.
+#[derive(Clone)]
+pub(super) struct Term {
+    sink:   Option<Sink>,
+    color:  bool,
+    json:   bool,
+}
+
+impl Term {
+    /// Output to `sink` (always uncolored), or else stderr, colored if `color`.  Also prints `--message-format json`
+    /// events to stdout if `json`.
+    pub(super) fn new(sink: Option<Sink>, color: bool, json: bool) -> Self { Self { color: color && sink.is_none(), sink, json } }
+
+    /// Are `--message-format json` events enabled?
+    pub(super) fn json(&self) -> bool { self.json }
+
+    /// The `--color` argument to pass to child cargos.
+    pub(super) fn cargo_color(&self) -> &'static str { if self.color { "always" } else { "never" } }
+
+    /// Write a line of output as-is (e.g. forwarded from cargo.)
+    pub(super) fn line(&self, line: &str) {
+        match self.sink.as_ref() {
+            Some(sink)  => sink(line),
+            None        => eprintln!("{}", line),
+        }
+    }
+
+    pub(super) fn error(&self, message: fmt::Arguments) {
+        if self.color { self.line(&format!("\u{001B}[31;1merror\u{001B}[37m:\u{001B}[0m {}", message)) } else { self.line(&format!("error: {}", message)) }
+    }
+
+    pub(super) fn warning(&self, message: fmt::Arguments) {
+        let message = message.to_string();
+        Event::new("warning").str("message", &message).emit(self);
+        if self.color { self.line(&format!("\u{001B}[33;1mwarning\u{001B}[37m:\u{001B}[0m {}", message)) } else { self.line(&format!("warning: {}", message)) }
+    }
+
+    pub(super) fn status(&self, verb: &str, message: fmt::Arguments) {
+        if self.color { self.line(&format!("\u{001B}[32;1m{: >12}\u{001B}[0m {}", verb, message)) } else { self.line(&format!("{: >12} {}", verb, message)) }
+    }
+}
+
+/// Resolve a `--color` choice (`None` deferring to `CARGO_TERM_COLOR`, then `auto`) to whether stderr gets colored.
+pub(super) fn color_choice(when: Option<&str>) -> Result<bool, String> {
+    let when = match when {
+        Some(when)  => when.to_string(),
+        None        => std::env::var("CARGO_TERM_COLOR").unwrap_or_default(),
+    };
+    match when.as_str() {
+        "" | "auto" => Ok(auto_color()),
+        "always"    => Ok(true),
+        "never"     => Ok(false),
+        other       => Err(format!("unknown color choice {:?}, expected auto, always, or never", other)),
+    }
+}
+
+fn auto_color() -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let dumb = std::env::var_os("TERM").is_some_and(|t| t == "dumb");
+    !no_color && !dumb && std::io::stderr().is_terminal()
+}
@@ -0,0 +1,129 @@
+//! `crates/<hash>/.checksums` (the SHA-256 and size of every bin a build produced) and `--verify`.
+
+use super::*;
+
+use std::fs::File;
+
+
+
+/// The checksums recorded for the bins of a single cache entry.
+pub(super) struct Checksums(BTreeMap<String, (String, u64)>);
+
+impl Checksums {
+    fn path(root: &Path) -> PathBuf { root.join(".checksums") }
+
+    /// Hash every bin in `root/bin` and record the results in `root/.checksums`.
+    pub(super) fn record(root: &Path) -> Result<(), Error> {
+        let bin = root.join("bin");
+        let mut text = String::new();
+        let entries = bin.read_dir().map_err(|err| error!(Cache, err, "unable to enumerate {}: {}", bin.display(), err))?;
+        let mut names = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|err| error!(Cache, err, "error enumerating {}: {}", bin.display(), err))?;
+            if entry.file_type().is_ok_and(|ft| ft.is_file()) { names.push(entry.file_name()); }
+        }
+        names.sort();
+        for name in names {
+            let path = bin.join(&name);
+            let (sha256, size) = hash_file(&path).map_err(|err| error!(Cache, err, "unable to hash {}: {}", path.display(), err))?;
+            writeln!(&mut text, "{}  {}  {}", sha256, size, name.to_string_lossy()).unwrap();
+        }
+        let path = Self::path(root);
+        std::fs::write(&path, text).map_err(|err| error!(Cache, err, "unable to write {}: {}", path.display(), err))
+    }
+
+    /// Read `root/.checksums`, or `None` if no checksums were recorded (e.g. the entry predates checksums).
+    pub(super) fn read(root: &Path) -> Option<Self> {
+        let text = std::fs::read_to_string(Self::path(root)).ok()?;
+        Some(Self(text.lines().filter_map(|line| {
+            let mut parts = line.splitn(3, "  ");
+            let sha256  = parts.next()?.to_string();
+            let size    = parts.next()?.parse().ok()?;
+            let name    = parts.next()?.to_string();
+            Some((name, (sha256, size)))
+        }).collect()))
+    }
+
+    /// Bin file names and their expected SHA-256 digests.
+    pub(super) fn bins(&self) -> impl Iterator<Item = (&str, &str)> { self.0.iter().map(|(name, (sha256, _))| (name.as_str(), sha256.as_str())) }
+
+    /// Describe every bin in `root/bin` that's missing, or has the wrong size or hash.
+    pub(super) fn check(&self, root: &Path) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (name, (sha256, size)) in self.0.iter() {
+            let path = root.join("bin").join(name);
+            match path.metadata() {
+                Err(err) => problems.push(format!("`{}`: {}", path.display(), err)),
+                Ok(meta) if meta.len() != *size => problems.push(format!("`{}`: expected {} bytes, found {}", path.display(), size, meta.len())),
+                Ok(_) => match hash_file(&path) {
+                    Err(err) => problems.push(format!("`{}`: {}", path.display(), err)),
+                    Ok((actual, _)) if actual != *sha256 => problems.push(format!("`{}`: expected SHA-256 {}, found {}", path.display(), sha256, actual)),
+                    Ok(_) => {},
+                },
+            }
+        }
+        problems
+    }
+}
+
+pub(super) fn hash_file(path: &Path) -> io::Result<(String, u64)> { sha256::hex_digest(File::open(path)?) }
+
+
+
+/// The outcome of `--verify`.
+pub(super) struct Verified {
+    /// Cache entries whose bins don't match their recorded checksums.
+    pub bad_entries:    Vec<PathBuf>,
+    /// Number of mismatched cached bins and missing or mismatched links.
+    pub problems:       usize,
+}
+
+/// Check every cache entry in `entries` against its recorded checksums, and every link recorded in each of `bins`
+/// (see [LinkRecords]) against the cache.  Problems are reported as warnings as they're found.
+pub(super) fn verify(context: &Context, entries: &[PathBuf], bins: &[PathBuf]) -> Verified {
+    let Context { quiet, verbose, ref term, .. } = *context;
+    let mut verified = Verified { bad_entries: Vec::new(), problems: 0 };
+    let mut checked_bins = 0;
+    let mut checked_links = 0;
+
+    for root in entries.iter() {
+        let checksums = match Checksums::read(root) {
+            Some(c) => c,
+            None => { if !quiet { warnln!(term, "no checksums recorded for `{}`", root.display()); } continue },
+        };
+        let problems = checksums.check(root);
+        checked_bins += checksums.0.len();
+        if verbose && problems.is_empty() { statusln!(term, "Verified", "`{}`", root.display()); }
+        if !problems.is_empty() {
+            for problem in problems.iter() { warnln!(term, "cached bin doesn't match its checksum: {}", problem); }
+            verified.problems += problems.len();
+            verified.bad_entries.push(root.clone());
+        }
+    }
+
+    for bin in bins.iter() {
+        for (name, mode, root) in LinkRecords::read(bin).entries() {
+            let checksums = match Checksums::read(&root) { Some(c) => c, None => continue };
+            for (file, sha256) in checksums.bins() {
+                checked_links += 1;
+                let dst = if mode == LinkMode::Shim { bin.join(link::shim_name(OsStr::new(file))) } else { bin.join(file) };
+                let problem = match (dst.exists(), mode) {
+                    (false, _)              => Some("missing (or dangling)".to_string()),
+                    (true, LinkMode::Shim)  => None, // launcher script, not a copy of the bin
+                    (true, _)               => match hash_file(&dst) {
+                        Err(err)                        => Some(err.to_string()),
+                        Ok((actual, _)) if actual != sha256 => Some(format!("expected SHA-256 {}, found {}", sha256, actual)),
+                        Ok(_)                           => None,
+                    },
+                };
+                if let Some(problem) = problem {
+                    warnln!(term, "`{}` (from `{}`): {}", dst.display(), name, problem);
+                    verified.problems += 1;
+                }
+            }
+        }
+    }
+
+    if !quiet { statusln!(term, "Verified", "{} cached bin(s) and {} link(s), {} problem(s) found", checked_bins, checked_links, verified.problems); }
+    verified
+}
@@ -0,0 +1,153 @@
+//! Shared fixtures: a [FakeCargo] that fakes `cargo install` without touching the network, and a [Sandbox] with its own
+//! `HOME`, `CARGO_HOME` (and thus cache), bin and target directories.
+
+#![allow(dead_code)] // not every test uses everything
+
+use cargo_local_install::*;
+
+use std::ffi::OsString;
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+
+
+/// Fakes `rustc [+toolchain] -vV`, `cargo [+toolchain] --version`, and `cargo [+toolchain] install ... --root X -- krate` by writing a
+/// fixture bin to `X/bin/krate` (`krate.exe` for `--target`s or hosts that are Windows, plus `X/.crates.toml`), printing roughly what cargo
+/// would.  Crates named `broken*` fail to build with exit code 101.
+#[derive(Clone, Default)]
+pub struct FakeCargo {
+    /// The arguments of every `cargo install`.
+    pub installs: Arc<Mutex<Vec<Vec<String>>>>,
+}
+
+impl FakeCargo {
+    pub fn install_count(&self) -> usize { self.installs.lock().unwrap().len() }
+}
+
+impl CargoRunner for FakeCargo {
+    fn spawn(&self, cmd: &mut Command) -> io::Result<CargoProcess> {
+        let args = cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect::<Vec<_>>();
+        let subcommand = args.iter().find(|a| !a.starts_with('+')).map(String::as_str); // skip `+toolchain`
+        let (stdout, stderr, code) = match subcommand {
+            Some("install")     => { self.installs.lock().unwrap().push(args.clone()); install(&args) },
+            Some("-vV")         => (String::from("rustc 1.95.0 (fake)\nbinary: rustc\nhost: x86_64-unknown-fake\nrelease: 1.95.0\n"), String::new(), 0),
+            Some("--version")   => (String::from("cargo 1.95.0 (fake)\n"), String::new(), 0),
+            _other              => panic!("unexpected command: {:?}", cmd),
+        };
+        Ok(CargoProcess {
+            stdout: Some(Box::new(Cursor::new(stdout))),
+            stderr: Some(Box::new(Cursor::new(stderr))),
+            wait:   Box::new(move || Ok(Some(code))),
+        })
+    }
+}
+
+fn install(args: &[String]) -> (String, String, i32) {
+    let arg = |flag: &str| args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).cloned();
+    let root = PathBuf::from(arg("--root").expect("--root"));
+    let krate = args.last().unwrap().clone();
+    let version = arg("--version").map_or_else(|| String::from("1.0.0"), |req| { // "^1.2" resolves to "1.2.0"
+        let mut parts = req.trim_start_matches(['^', '=', '~']).split('.').map(String::from).collect::<Vec<_>>();
+        parts.resize(3, String::from("0"));
+        parts.join(".")
+    });
+    let force = args.iter().any(|a| a == "--force");
+
+    if krate.starts_with("broken") {
+        let diagnostic = "error[E0425]: cannot find value `x` in this scope\n --> src/main.rs:1:13\n";
+        let compiling = format!("   Compiling {k} v{v}\n", k = krate, v = version);
+        let could_not = format!("error: could not compile `{}`\n", krate);
+        return if args.iter().any(|a| a == "--message-format") { // diagnostics move to stdout
+            let json = format!("{{\"reason\":\"compiler-message\",\"message\":{{\"children\":[{{\"rendered\":null}}],\"rendered\":{:?}}}}}\n", diagnostic);
+            (json, compiling + &could_not, 101)
+        } else {
+            (String::new(), compiling + diagnostic + &could_not, 101)
+        };
+    }
+
+    let windows = arg("--target").map_or(cfg!(windows), |target| target.contains("windows"));
+    let exe = format!("{}{}", krate, if windows { ".exe" } else { "" });
+    let bin = root.join("bin").join(&exe);
+    if bin.exists() && !force {
+        return (String::new(), format!("     Ignored package `{} v{}` is already installed, use --force to override\n", krate, version), 0);
+    }
+    std::fs::create_dir_all(root.join("bin")).unwrap();
+    std::fs::write(&bin, format!("fake {} {}\n", krate, version)).unwrap();
+    std::fs::write(root.join(".crates.toml"), format!("[v1]\n\"{} {} (registry+https://github.com/rust-lang/crates.io-index)\" = [\"{}\"]\n", krate, version, exe)).unwrap();
+
+    let stderr = format!(concat!(
+        "  Installing {k} v{v}\n",
+        "   Compiling {k} v{v}\n",
+        "    Finished `release` profile [optimized] target(s) in 0.00s\n",
+        "  Installing {bin}\n",
+        "   Installed package `{k} v{v}` (executable `{exe}`)\n",
+        "warning: be sure to add `{root}` to your PATH to be able to run the installed binaries\n",
+    ), k = krate, v = version, bin = bin.display(), exe = exe, root = root.join("bin").display());
+    (String::new(), stderr, 0)
+}
+
+
+
+/// Installs read environment variables like `HOME`, which tests change, so only one [Sandbox] exists at a time.
+static SERIAL : Mutex<()> = Mutex::new(());
+
+/// An empty directory to work in, doubling as `HOME` (with `CARGO_HOME` in `.cargo`) so the cache starts out empty.
+pub struct Sandbox {
+    pub dir:    PathBuf,
+    pub cargo:  FakeCargo,
+    pub output: Arc<Mutex<Vec<String>>>,
+    _serial:    MutexGuard<'static, ()>,
+}
+
+impl Sandbox {
+    pub fn new(name: &str) -> Self {
+        let serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let dir = std::env::temp_dir().join("cargo-local-install-tests").join(format!("{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("target")).unwrap();
+        let dir = dir.canonicalize().unwrap();
+        std::env::set_var("HOME", &dir);
+        std::env::set_var("USERPROFILE", &dir);
+        std::env::set_var("CARGO_HOME", dir.join(".cargo"));
+        std::env::remove_var("CARGO_LOCAL_INSTALL_HOME");
+        std::env::remove_var("CARGO_TERM_COLOR");
+        Self { dir, cargo: FakeCargo::default(), output: Default::default(), _serial: serial }
+    }
+
+    /// Options installing into this sandbox with [FakeCargo], capturing output.
+    pub fn options(&self) -> InstallOptions {
+        let output = self.output.clone();
+        InstallOptions::new()
+            .runner(self.cargo.clone())
+            .target_dir(self.dir.join("target"))
+            .locked(true)
+            .log_to(move |line| output.lock().unwrap().push(line.to_string()))
+    }
+
+    pub fn install(&self, options: InstallOptions) -> Result<InstallReport, Error> {
+        self.output.lock().unwrap().clear();
+        Installer::new(options).install()
+    }
+
+    pub fn output(&self) -> Vec<String> { self.output.lock().unwrap().clone() }
+
+    pub fn path(&self, path: impl AsRef<Path>) -> PathBuf { self.dir.join(path) }
+
+    /// Write a `Cargo.toml` into `dir` with `metadata` as its `[package.metadata.local-install]`.
+    pub fn manifest(&self, dir: &str, metadata: &str) -> PathBuf {
+        let dir = self.path(dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), format!("[package]\nname = \"project\"\nversion = \"0.1.0\"\n\n[package.metadata.local-install]\n{}\n", metadata)).unwrap();
+        dir
+    }
+}
+
+/// The `(name, outcome)` of every tool in `report`.
+pub fn outcomes(report: &InstallReport) -> Vec<(String, Outcome)> {
+    report.tools().map(|tool| (tool.name.clone(), tool.outcome)).collect()
+}
+
+/// Where `link` ultimately points.
+pub fn target(link: &Path) -> OsString { std::fs::canonicalize(link).unwrap().into_os_string() }
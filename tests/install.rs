@@ -0,0 +1,355 @@
+//! The whole install pipeline (manifest discovery, cache keys, `.built` stamps, linking, output filtering) against
+//! [common::FakeCargo], without network access or real builds.
+
+mod common;
+use common::*;
+
+use cargo_local_install::*;
+
+use std::sync::{Arc, Mutex};
+
+
+
+#[test]
+fn installs_and_links_tools() {
+    let sandbox = Sandbox::new("installs_and_links_tools");
+    let report = sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool-a")).tool(Tool::new("tool-b").version("2.0"))).unwrap();
+
+    assert_eq!(outcomes(&report), vec![(String::from("tool-a"), Outcome::Built), (String::from("tool-b"), Outcome::Built)]);
+    assert_eq!(sandbox.cargo.install_count(), 2);
+
+    let tool_b = report.tools().find(|tool| tool.name == "tool-b").unwrap();
+    assert_eq!(tool_b.requested_version, "2.0");
+    assert_eq!(tool_b.resolved_version.as_deref(), Some("2.0.0"));
+    assert!(tool_b.cache_dir.starts_with(sandbox.path(".cargo/local-install/crates")));
+
+    let bin = report.bin("tool-b").unwrap();
+    assert_eq!(bin.parent(), Some(sandbox.path("bin").as_path()));
+    assert_eq!(std::fs::read_to_string(bin).unwrap(), "fake tool-b 2.0.0\n");
+    assert!(std::path::Path::new(&target(bin)).starts_with(&tool_b.cache_dir));
+}
+
+#[test]
+fn reuses_cache_entries_across_bin_dirs() {
+    let sandbox = Sandbox::new("reuses_cache_entries_across_bin_dirs");
+    let a = sandbox.install(sandbox.options().bin_dir(sandbox.path("a/bin")).tool(Tool::new("tool"))).unwrap();
+    let b = sandbox.install(sandbox.options().bin_dir(sandbox.path("b/bin")).tool(Tool::new("tool")).verbosity(Verbosity::Verbose).jobs(3)).unwrap();
+
+    assert_eq!(outcomes(&a), vec![(String::from("tool"), Outcome::Built)]);
+    assert_eq!(outcomes(&b), vec![(String::from("tool"), Outcome::Cached)]);
+    assert_eq!(a.tools().next().unwrap().cache_dir, b.tools().next().unwrap().cache_dir);
+    assert_eq!(target(a.bin("tool").unwrap()), target(b.bin("tool").unwrap()));
+}
+
+#[test]
+fn cache_dir_precedence() {
+    let sandbox = Sandbox::new("cache_dir_precedence");
+    let cache_dir = |options: InstallOptions| sandbox.install(options.bin_dir(sandbox.path("bin")).tool(Tool::new("tool"))).unwrap().tools().next().unwrap().cache_dir.clone();
+
+    std::env::remove_var("CARGO_HOME");
+    assert!(cache_dir(sandbox.options()).starts_with(sandbox.path(".cargo/local-install/crates")));
+
+    std::env::set_var("CARGO_HOME", sandbox.path("cargo-home"));
+    assert!(cache_dir(sandbox.options()).starts_with(sandbox.path("cargo-home/local-install/crates")));
+    assert!(cache_dir(sandbox.options().cache_dir(sandbox.path("option"))).starts_with(sandbox.path("option/crates")));
+
+    std::env::set_var("CARGO_LOCAL_INSTALL_HOME", sandbox.path("env"));
+    assert!(cache_dir(sandbox.options().cache_dir(sandbox.path("option"))).starts_with(sandbox.path("env/crates")));
+
+    std::env::remove_var("CARGO_LOCAL_INSTALL_HOME");
+    std::env::remove_var("CARGO_HOME");
+    std::env::remove_var("HOME");
+    std::env::remove_var("USERPROFILE");
+    let err = sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool"))).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Env);
+}
+
+#[test]
+fn unwritable_cache_dir_fails_early() {
+    let sandbox = Sandbox::new("unwritable_cache_dir_fails_early");
+    std::fs::write(sandbox.path("file"), "").unwrap();
+    let err = sandbox.install(sandbox.options().cache_dir(sandbox.path("file/cache")).bin_dir(sandbox.path("bin")).tool(Tool::new("tool"))).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Cache);
+    assert_eq!(sandbox.cargo.install_count(), 0);
+}
+
+#[test]
+fn cache_key_includes_build_affecting_flags() {
+    let sandbox = Sandbox::new("cache_key_includes_build_affecting_flags");
+    let cache_dir = |tool: Tool| sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(tool)).unwrap().tools().next().unwrap().cache_dir.clone();
+
+    let v1          = cache_dir(Tool::new("tool").version("1.0"));
+    let v2          = cache_dir(Tool::new("tool").version("2.0"));
+    let features    = cache_dir(Tool::new("tool").version("1.0").features(["extra"]));
+    assert_ne!(v1, v2);
+    assert_ne!(v1, features);
+    assert_eq!(v1, cache_dir(Tool::new("tool").version("1.0")));
+}
+
+#[test]
+fn cache_key_includes_build_affecting_env() {
+    let sandbox = Sandbox::new("cache_key_includes_build_affecting_env");
+    let cache_dir = |var: &str| {
+        std::env::set_var(var, "-C opt-level=1");
+        let cache_dir = sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool"))).unwrap().tools().next().unwrap().cache_dir.clone();
+        std::env::remove_var(var);
+        cache_dir
+    };
+
+    let plain = sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool"))).unwrap().tools().next().unwrap().cache_dir.clone();
+    assert_ne!(plain, cache_dir("RUSTFLAGS"));
+    assert_ne!(plain, cache_dir("CARGO_TARGET_X86_64_UNKNOWN_LINUX_GNU_RUSTFLAGS"));
+    assert_ne!(plain, cache_dir("CARGO_TARGET_AARCH64_APPLE_DARWIN_LINKER"));
+    assert_eq!(plain, cache_dir("CARGO_TARGET_DIR"));
+}
+
+#[test]
+fn toolchains_get_their_own_cache_entries() {
+    let sandbox = Sandbox::new("toolchains_get_their_own_cache_entries");
+    let project = sandbox.manifest("project", "tool-a = \"1\"\ntool-b = { version = \"1\", toolchain = \"nightly\" }");
+    let report = sandbox.install(sandbox.options().manifest_dir(&project)).unwrap();
+    let installs = sandbox.cargo.installs.lock().unwrap().clone();
+    assert_eq!(installs.iter().map(|args| args[0].as_str()).collect::<Vec<_>>(), ["install", "+nightly"]);
+
+    let cache_dir = |report: &InstallReport, name: &str| report.tools().find(|tool| tool.name == name).unwrap().cache_dir.clone();
+    let stable  = sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool-b").version("^1"))).unwrap();
+    let nightly = sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool-b").version("^1")).toolchain("nightly")).unwrap();
+    assert_ne!(cache_dir(&stable, "tool-b"), cache_dir(&report, "tool-b"));
+    assert_eq!(cache_dir(&nightly, "tool-b"), cache_dir(&report, "tool-b"));
+    assert_eq!(outcomes(&nightly), vec![(String::from("tool-b"), Outcome::Cached)]);
+}
+
+#[test]
+fn installs_manifest_metadata_once() {
+    let sandbox = Sandbox::new("installs_manifest_metadata_once");
+    let project = sandbox.manifest("project", "tool = \"1.2\"\nrenamed = { package = \"other-tool\", version = \"3\" }");
+
+    let first = sandbox.install(sandbox.options().manifest_dir(project.join("src"))).unwrap();
+    assert_eq!(outcomes(&first), vec![(String::from("other-tool"), Outcome::Built), (String::from("tool"), Outcome::Built)]);
+    assert_eq!(first.sets.len(), 1);
+    assert_eq!(first.sets[0].bin_dir, project.join("bin"));
+    assert_eq!(first.sets[0].manifest.as_deref(), Some(project.join("Cargo.toml").as_path()));
+    assert!(project.join("bin/.built").exists());
+
+    // `.built` is newer than `Cargo.toml`: nothing to do
+    let second = sandbox.install(sandbox.options().manifest_dir(&project)).unwrap();
+    assert_eq!(outcomes(&second), vec![(String::from("other-tool"), Outcome::Skipped), (String::from("tool"), Outcome::Skipped)]);
+    assert_eq!(sandbox.cargo.install_count(), 2);
+    assert_eq!(second.bin("tool"), first.bin("tool"));
+}
+
+#[test]
+fn repairs_dangling_links() {
+    let sandbox = Sandbox::new("repairs_dangling_links");
+    let project = sandbox.manifest("project", "tool-a = \"1\"\ntool-b = \"1\"");
+    let first = sandbox.install(sandbox.options().manifest_dir(&project)).unwrap();
+
+    let tool_a = first.tools().find(|tool| tool.name == "tool-a").unwrap();
+    std::fs::remove_dir_all(&tool_a.cache_dir).unwrap();
+
+    let second = sandbox.install(sandbox.options().manifest_dir(&project)).unwrap();
+    assert_eq!(outcomes(&second), vec![(String::from("tool-a"), Outcome::Built), (String::from("tool-b"), Outcome::Skipped)]);
+    assert_eq!(sandbox.cargo.install_count(), 3);
+    assert_eq!(std::fs::read_to_string(second.bin("tool-a").unwrap()).unwrap(), "fake tool-a 1.0.0\n");
+    assert!(sandbox.output().iter().any(|line| line.contains("Repairing") && line.contains("tool-a")));
+}
+
+#[test]
+fn repairs_missing_links_into_entries_without_checksums() {
+    let sandbox = Sandbox::new("repairs_missing_links_into_entries_without_checksums");
+    let project = sandbox.manifest("project", "tool = \"1\"");
+    let first = sandbox.install(sandbox.options().manifest_dir(&project)).unwrap();
+    std::fs::remove_file(first.tools().next().unwrap().cache_dir.join(".checksums")).unwrap(); // as if built by an older version
+    std::fs::remove_file(first.bin("tool").unwrap()).unwrap();
+    let an_hour_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    std::fs::File::options().write(true).open(project.join("Cargo.toml")).unwrap().set_modified(an_hour_ago).unwrap(); // so only the missing link is stale
+
+    let second = sandbox.install(sandbox.options().manifest_dir(&project)).unwrap();
+    assert_eq!(outcomes(&second), vec![(String::from("tool"), Outcome::Cached)]);
+    assert!(second.bin("tool").unwrap().exists());
+    assert!(sandbox.output().iter().any(|line| line.contains("Repairing") && line.contains("tool")));
+}
+
+#[test]
+fn filters_cargo_output() {
+    let sandbox = Sandbox::new("filters_cargo_output");
+    let report = sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool").version("1.5"))).unwrap();
+    let cache_dir = report.tools().next().unwrap().cache_dir.display().to_string();
+    let output = sandbox.output();
+
+    assert!(output.iter().any(|line| line.contains("Installing <cache>/tool-1.5.0")), "{:#?}", output);
+    assert!(!output.iter().any(|line| line.contains(&cache_dir)), "{:#?}", output);
+    assert!(!output.iter().any(|line| line.contains("`release` profile")), "{:#?}", output);
+    assert!(!output.iter().any(|line| line.contains("to your PATH")), "{:#?}", output);
+
+    // cargo's "already installed" noise is dropped too
+    sandbox.install(sandbox.options().bin_dir(sandbox.path("bin2")).tool(Tool::new("tool").version("1.5"))).unwrap();
+    let output = sandbox.output();
+    assert!(!output.iter().any(|line| line.contains("Ignored")), "{:#?}", output);
+
+    let log = std::fs::read_to_string(format!("{}.log", cache_dir)).unwrap();
+    assert!(log.contains("Ignored package `tool v1.5.0` is already installed"), "{}", log);
+}
+
+#[test]
+fn sinks_are_never_colored() {
+    let sandbox = Sandbox::new("sinks_are_never_colored");
+    std::env::set_var("CARGO_TERM_COLOR", "always");
+    sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool"))).unwrap();
+    std::env::remove_var("CARGO_TERM_COLOR");
+
+    let output = sandbox.output();
+    assert!(output.iter().any(|line| line.starts_with("    Finished installing")), "{:#?}", output);
+    assert!(!output.iter().any(|line| line.contains('\u{1B}')), "{:#?}", output);
+    let args = sandbox.cargo.installs.lock().unwrap()[0].clone();
+    assert!(args.windows(2).any(|w| w == ["--color", "never"]), "{:?}", args);
+}
+
+#[test]
+fn concurrent_installers_keep_their_own_output() {
+    let sandbox = Sandbox::new("concurrent_installers_keep_their_own_output");
+    let outputs = [Arc::new(Mutex::new(Vec::new())), Arc::new(Mutex::new(Vec::new()))];
+    std::thread::scope(|scope| for (i, output) in outputs.iter().enumerate() {
+        let output = output.clone();
+        let options = sandbox.options()
+            .bin_dir(sandbox.path(format!("bin-{}", i)))
+            .tool(Tool::new(format!("tool-{}", i)))
+            .log_to(move |line| output.lock().unwrap().push(line.to_string()));
+        scope.spawn(move || for _ in 0 .. 10 { Installer::new(options.clone().force(true)).install().unwrap(); });
+    });
+    for (i, output) in outputs.iter().enumerate() {
+        let output = output.lock().unwrap();
+        assert!(output.iter().any(|line| line.contains(&format!("tool-{}", i))), "{:#?}", output);
+        assert!(!output.iter().any(|line| line.contains(&format!("tool-{}", 1 - i))), "{:#?}", output);
+    }
+}
+
+#[test]
+fn reports_build_failures() {
+    let sandbox = Sandbox::new("reports_build_failures");
+    let err = sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("broken"))).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BuildFailed { exit_code: Some(101) });
+    assert_eq!(err.tool(), Some("broken"));
+    assert_eq!(err.exit_code(), 101);
+    assert!(sandbox.output().iter().any(|line| line.contains("could not compile `broken`")));
+}
+
+#[test]
+fn build_failures_name_their_install_set() {
+    let sandbox = Sandbox::new("build_failures_name_their_install_set");
+    let project = sandbox.manifest("project", "broken = \"1\"");
+    let err = sandbox.install(sandbox.options().manifest_dir(&project)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::BuildFailed { exit_code: Some(101) });
+    assert_eq!(err.tool(), Some("broken"));
+    assert_eq!(err.install_set(), Some(project.join("Cargo.toml").as_path()));
+}
+
+#[test]
+fn quiet_builds_still_log_everything() {
+    let sandbox = Sandbox::new("quiet_builds_still_log_everything");
+    let report = sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool")).verbosity(Verbosity::Quiet)).unwrap();
+    assert!(!sandbox.cargo.installs.lock().unwrap()[0].iter().any(|arg| arg == "--quiet"));
+    assert!(!sandbox.output().iter().any(|line| line.contains("Compiling")));
+    let log = std::fs::read_to_string(report.tools().next().unwrap().cache_dir.with_extension("log")).unwrap();
+    assert!(log.contains("Compiling tool v1.0.0"), "{}", log);
+}
+
+#[test]
+fn json_keeps_diagnostics_in_human_output_and_logs() {
+    let sandbox = Sandbox::new("json_keeps_diagnostics_in_human_output_and_logs");
+    for quiet in [false, true] {
+        let options = sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("broken")).message_format_json(true);
+        let options = if quiet { options.verbosity(Verbosity::Quiet) } else { options };
+        sandbox.install(options).unwrap_err();
+        let output = sandbox.output();
+        assert!(output.iter().any(|line| line == "error[E0425]: cannot find value `x` in this scope"), "{:#?}", output);
+        assert!(output.iter().any(|line| line == " --> src/main.rs:1:13"), "{:#?}", output);
+    }
+
+    let log = sandbox.path(".cargo/local-install/crates").read_dir().unwrap().filter_map(|e| e.ok()).map(|e| e.path()).find(|p| p.extension().is_some_and(|ext| ext == "log")).unwrap();
+    let log = std::fs::read_to_string(log).unwrap();
+    assert!(log.contains("error[E0425]: cannot find value `x` in this scope\n --> src/main.rs:1:13\n"), "{}", log);
+    assert!(log.contains("could not compile `broken`"), "{}", log);
+}
+
+#[test]
+fn show_log_needs_only_the_crate() {
+    let sandbox = Sandbox::new("show_log_needs_only_the_crate");
+    let show_log = |krate: &str| run_from_strs_with(sandbox.options(), ["--show-log", krate].iter());
+    assert_eq!(show_log("broken").unwrap_err().kind(), ErrorKind::Io);
+
+    let project = sandbox.manifest("project", "broken = { version = \"1\", features = [\"extra\"] }");
+    sandbox.install(sandbox.options().manifest_dir(&project)).unwrap_err();
+    let log = std::fs::read_to_string(sandbox.path(".cargo/local-install/logs/broken")).unwrap();
+    assert!(std::fs::read_to_string(log.trim_end()).unwrap().contains("could not compile `broken`"));
+    sandbox.output.lock().unwrap().clear();
+    show_log("broken").unwrap(); // without the project, or its flags
+    assert!(sandbox.output().iter().any(|line| line.contains("could not compile `broken`")));
+}
+
+#[test]
+fn links_what_built_before_the_first_failure() {
+    let sandbox = Sandbox::new("links_what_built_before_the_first_failure");
+    let err = sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool")).tool(Tool::new("broken"))).unwrap_err();
+    assert_eq!(err.tool(), Some("broken"));
+    assert_eq!(err.kind(), ErrorKind::BuildFailed { exit_code: Some(101) });
+    assert_eq!(err.exit_code(), 10);
+    assert!(sandbox.path("bin/tool").with_extension(std::env::consts::EXE_EXTENSION).exists());
+}
+
+#[test]
+fn parallel_builds_get_their_own_target_dirs_and_jobs() {
+    let sandbox = Sandbox::new("parallel_builds_get_their_own_target_dirs_and_jobs");
+    let tools = ["tool-a", "tool-b", "tool-c"].iter().map(|name| Tool::new(*name));
+    let report = sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tools(tools).parallel(2).jobs(5)).unwrap();
+    assert_eq!(outcomes(&report).len(), 3);
+
+    let installs = sandbox.cargo.installs.lock().unwrap().clone();
+    let arg = |install: &Vec<String>, flag: &str| install.iter().position(|a| a == flag).map(|i| install[i + 1].clone()).unwrap();
+    let mut target_dirs = installs.iter().map(|install| arg(install, "--target-dir")).collect::<Vec<_>>();
+    target_dirs.sort();
+    target_dirs.dedup();
+    assert!(target_dirs.iter().all(|dir| [sandbox.path("target"), sandbox.path("target-1")].iter().any(|t| *dir == t.display().to_string())), "{:?}", target_dirs);
+    assert!(installs.iter().all(|install| arg(install, "--jobs") == "2"), "{:?}", installs); // 5 jobs split between 2 workers
+
+    let output = sandbox.output();
+    for name in ["tool-a", "tool-b", "tool-c"].iter() {
+        assert!(output.iter().any(|line| line.starts_with(&format!("[{}]", name)) && line.contains("Compiling")), "{:#?}", output);
+    }
+}
+
+#[test]
+fn serial_builds_share_a_target_dir_without_prefixes() {
+    let sandbox = Sandbox::new("serial_builds_share_a_target_dir_without_prefixes");
+    let tools = ["tool-a", "tool-b"].iter().map(|name| Tool::new(*name));
+    sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tools(tools).jobs(5)).unwrap();
+
+    for install in sandbox.cargo.installs.lock().unwrap().iter() {
+        assert!(install.windows(2).any(|w| w[0] == "--target-dir" && w[1] == sandbox.path("target").display().to_string()), "{:?}", install);
+        assert!(install.windows(2).any(|w| w[0] == "--jobs" && w[1] == "5"), "{:?}", install);
+    }
+    assert!(!sandbox.output().iter().any(|line| line.starts_with('[')));
+}
+
+#[test]
+fn keep_going_installs_the_rest() {
+    let sandbox = Sandbox::new("keep_going_installs_the_rest");
+    let project = sandbox.manifest("project", "broken = \"1\"\ntool = \"1\"");
+    let err = sandbox.install(sandbox.options().manifest_dir(&project).keep_going(true)).unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::BuildFailed { exit_code: Some(101) });
+    assert_eq!(err.exit_code(), 10); // ambiguous with multiple builds
+    assert_eq!(err.tool(), Some("broken"));
+    assert_eq!(err.install_set(), Some(project.join("Cargo.toml").as_path()));
+    assert!(project.join("bin/tool").with_extension(std::env::consts::EXE_EXTENSION).exists());
+    assert!(!project.join("bin/.built").exists()); // retry next time
+}
+
+#[test]
+fn dry_run_installs_nothing() {
+    let sandbox = Sandbox::new("dry_run_installs_nothing");
+    let report = sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool")).dry_run(true)).unwrap();
+    assert_eq!(outcomes(&report), vec![(String::from("tool"), Outcome::Skipped)]);
+    assert_eq!(sandbox.cargo.install_count(), 0);
+    assert!(!sandbox.path("bin").exists());
+}
@@ -0,0 +1,146 @@
+//! Putting cached bins into bin directories: every `--link-mode`, shims, relinking, and checksums.
+
+mod common;
+use common::*;
+
+use cargo_local_install::*;
+
+
+
+/// Install `tool` into `bin` with `mode` three times (relinking the same cache entry), returning the link.
+fn install_thrice(sandbox: &Sandbox, mode: LinkMode) -> std::path::PathBuf {
+    for _ in 0 .. 3 {
+        sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool")).link_mode(mode).force(true)).unwrap();
+    }
+    let leftovers = sandbox.path("bin").read_dir().unwrap().filter_map(|e| e.ok()).map(|e| e.file_name().to_string_lossy().into_owned()).filter(|name| name.ends_with(".tmp")).collect::<Vec<_>>();
+    assert_eq!(leftovers, Vec::<String>::new());
+    sandbox.path("bin").join(format!("tool{}", std::env::consts::EXE_SUFFIX))
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink() {
+    let sandbox = Sandbox::new("link_symlink");
+    let link = install_thrice(&sandbox, LinkMode::Symlink);
+    let target = std::fs::read_link(&link).unwrap();
+    assert!(target.is_absolute() && target.starts_with(sandbox.path(".cargo/local-install/crates")), "{}", target.display());
+    assert_eq!(std::fs::read_to_string(&link).unwrap(), "fake tool 1.0.0\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn relative_symlink() {
+    let sandbox = Sandbox::new("link_relative_symlink");
+    let link = install_thrice(&sandbox, LinkMode::RelativeSymlink);
+    let target = std::fs::read_link(&link).unwrap();
+    assert!(target.is_relative() && target.starts_with(".."), "{}", target.display());
+    assert_eq!(std::fs::read_to_string(&link).unwrap(), "fake tool 1.0.0\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn hardlink() {
+    use std::os::unix::fs::MetadataExt;
+    let sandbox = Sandbox::new("link_hardlink");
+    let link = install_thrice(&sandbox, LinkMode::Hardlink);
+    let meta = std::fs::symlink_metadata(&link).unwrap();
+    assert!(meta.file_type().is_file());
+    assert_eq!(meta.nlink(), 2); // the cache entry's bin, and ours
+}
+
+#[test]
+fn copy() {
+    let sandbox = Sandbox::new("link_copy");
+    let link = install_thrice(&sandbox, LinkMode::Copy);
+    assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_file());
+    assert_eq!(std::fs::read_to_string(&link).unwrap(), "fake tool 1.0.0\n");
+}
+
+#[test]
+fn changing_modes_replaces_links() {
+    let sandbox = Sandbox::new("link_changing_modes_replaces_links");
+    let project = sandbox.manifest("project", "tool = { version = \"1\", link-mode = \"copy\" }");
+    sandbox.install(sandbox.options().manifest_dir(&project)).unwrap();
+    let link = project.join("bin").join(format!("tool{}", std::env::consts::EXE_SUFFIX));
+    assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_file());
+
+    sandbox.manifest("project", "tool = { version = \"1\", link-mode = \"shim\" }");
+    let report = sandbox.install(sandbox.options().manifest_dir(&project)).unwrap();
+    assert_eq!(outcomes(&report), vec![(String::from("tool"), Outcome::Cached)]);
+    assert!(std::fs::read_to_string(project.join("bin/tool")).unwrap().starts_with("#!/bin/sh\n"));
+    assert!(std::fs::read_to_string(project.join("bin/.links")).unwrap().contains("shim"));
+}
+
+#[test]
+fn changing_modes_removes_links_that_would_shadow_shims() {
+    let sandbox = Sandbox::new("link_changing_modes_removes_links_that_would_shadow_shims");
+    let bin = sandbox.path("bin");
+    let install = |mode: &str| run_from_strs_with(sandbox.options(), ["--target", "x86_64-pc-windows-msvc", "--link-mode", mode, "--out-bin", bin.to_str().unwrap(), "tool"].iter()).unwrap();
+    let files = || { let mut files = bin.read_dir().unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).filter(|f| !f.starts_with('.')).collect::<Vec<_>>(); files.sort(); files };
+
+    install("copy");
+    assert_eq!(files(), ["tool.exe"]);
+    install("shim");
+    assert_eq!(files(), ["tool", "tool.cmd"]);
+    assert!(std::fs::read_to_string(bin.join("tool.cmd")).unwrap().contains("tool.exe\" %*\r\n"));
+    install("copy");
+    assert_eq!(files(), ["tool.exe"]);
+}
+
+#[test]
+fn shims_set_env_and_args() {
+    let sandbox = Sandbox::new("link_shims_set_env_and_args");
+    let project = sandbox.manifest("project", "tool = { version = \"1\", env = { TOOL_CACHE = \"it's here\" }, args = [\"--flag\"] }");
+    let report = sandbox.install(sandbox.options().manifest_dir(&project)).unwrap();
+
+    let shim = project.join("bin/tool");
+    let script = std::fs::read_to_string(&shim).unwrap();
+    assert!(script.contains("TOOL_CACHE='it'\\''s here'; export TOOL_CACHE\n"), "{}", script);
+    assert!(script.contains("'--flag' \"$@\"\n"), "{}", script);
+    assert!(script.contains(&*report.tools().next().unwrap().cache_dir.to_string_lossy()), "{}", script);
+    #[cfg(unix)] {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(std::fs::metadata(&shim).unwrap().permissions().mode() & 0o111, 0o111);
+    }
+    assert_eq!(project.join("bin/tool.cmd").exists(), cfg!(windows)); // only for `.exe`s
+}
+
+#[test]
+fn rebuilds_corrupted_bins() {
+    let sandbox = Sandbox::new("link_rebuilds_corrupted_bins");
+    let first = sandbox.install(sandbox.options().bin_dir(sandbox.path("a")).tool(Tool::new("tool"))).unwrap();
+    let cached = first.tools().next().unwrap().cache_dir.join("bin").join(format!("tool{}", std::env::consts::EXE_SUFFIX));
+    std::fs::write(&cached, "tampered").unwrap();
+
+    let report = sandbox.install(sandbox.options().bin_dir(sandbox.path("b")).tool(Tool::new("tool")).link_mode(LinkMode::Copy)).unwrap();
+    assert_eq!(outcomes(&report), vec![(String::from("tool"), Outcome::Built)]);
+    assert!(sandbox.cargo.installs.lock().unwrap()[1].iter().any(|arg| arg == "--force"));
+    assert!(sandbox.output().iter().any(|line| line.contains("doesn't match its checksum, rebuilding")), "{:#?}", sandbox.output());
+    assert_eq!(std::fs::read_to_string(report.bin("tool").unwrap()).unwrap(), "fake tool 1.0.0\n");
+}
+
+#[test]
+fn cache_hits_keep_their_checksums() {
+    let sandbox = Sandbox::new("link_cache_hits_keep_their_checksums");
+    let first = sandbox.install(sandbox.options().bin_dir(sandbox.path("a")).tool(Tool::new("tool"))).unwrap();
+    let checksums = first.tools().next().unwrap().cache_dir.join(".checksums");
+    let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+    std::fs::File::options().write(true).open(&checksums).unwrap().set_modified(old).unwrap();
+
+    let report = sandbox.install(sandbox.options().bin_dir(sandbox.path("b")).tool(Tool::new("tool"))).unwrap();
+    assert_eq!(outcomes(&report), vec![(String::from("tool"), Outcome::Cached)]);
+    assert_eq!(checksums.metadata().unwrap().modified().unwrap(), old); // not rehashed
+}
+
+#[test]
+fn verify_finds_corrupted_bins() {
+    let sandbox = Sandbox::new("link_verify_finds_corrupted_bins");
+    let bin = sandbox.path("bin");
+    let first = sandbox.install(sandbox.options().bin_dir(&bin).tool(Tool::new("tool"))).unwrap();
+    let verify = || run_from_strs_with(sandbox.options(), ["--verify", "--locked", "--out-bin", bin.to_str().unwrap(), "tool"].iter());
+
+    verify().unwrap();
+    let cached = first.tools().next().unwrap().cache_dir.join("bin").join(format!("tool{}", std::env::consts::EXE_SUFFIX));
+    std::fs::write(&cached, "tampered").unwrap();
+    assert_eq!(verify().unwrap_err().kind(), ErrorKind::Verify);
+}
@@ -0,0 +1,67 @@
+//! `.lock` files next to cache entries: waiting for live locks, and breaking stale ones.
+
+mod common;
+use common::*;
+
+use cargo_local_install::*;
+
+use std::time::{Duration, SystemTime};
+
+
+
+#[test]
+fn waits_for_fresh_locks_of_unknown_processes() {
+    let sandbox = Sandbox::new("waits_for_fresh_locks_of_unknown_processes");
+    let first = sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool"))).unwrap();
+    let lock = first.tools().next().unwrap().cache_dir.with_extension("lock");
+    std::fs::write(&lock, "4000000000\n").unwrap(); // not a PID of ours, e.g. another container's
+
+    std::thread::scope(|scope| {
+        let install = scope.spawn(|| sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool")).force(true)));
+        std::thread::sleep(Duration::from_millis(1000));
+        assert_eq!(sandbox.cargo.install_count(), 1, "lock wasn't respected");
+        assert!(lock.exists());
+        std::fs::remove_file(&lock).unwrap();
+        install.join().unwrap().unwrap();
+    });
+    assert_eq!(sandbox.cargo.install_count(), 2);
+    assert!(sandbox.output().iter().any(|line| line.contains("Waiting") && line.contains("4000000000")));
+}
+
+#[test]
+fn breaks_stale_locks() {
+    let sandbox = Sandbox::new("breaks_stale_locks");
+    let first = sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool"))).unwrap();
+    let lock = first.tools().next().unwrap().cache_dir.with_extension("lock");
+    std::fs::write(&lock, "4000000000\n").unwrap();
+    std::fs::File::options().write(true).open(&lock).unwrap().set_modified(SystemTime::now() - Duration::from_secs(60)).unwrap();
+
+    sandbox.install(sandbox.options().bin_dir(sandbox.path("bin")).tool(Tool::new("tool")).force(true)).unwrap();
+    assert_eq!(sandbox.cargo.install_count(), 2);
+    assert!(sandbox.output().iter().any(|line| line.contains("broke stale lock")), "{:#?}", sandbox.output());
+    assert!(!lock.exists());
+    let leftovers = lock.parent().unwrap().read_dir().unwrap().filter_map(|e| e.ok()).filter(|e| e.file_name().to_string_lossy().contains(".stale-")).count();
+    assert_eq!(leftovers, 0);
+}
+
+#[test]
+fn releasing_leaves_locks_taken_over_by_others() {
+    /// Breaks our lock mid-build, as another process would if we stalled long enough to look stale, and takes it over.
+    #[derive(Clone)] struct Usurper(FakeCargo);
+    impl CargoRunner for Usurper {
+        fn spawn(&self, cmd: &mut std::process::Command) -> std::io::Result<CargoProcess> {
+            let args = cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect::<Vec<_>>();
+            if let Some(root) = args.iter().position(|a| a == "--root").map(|i| std::path::PathBuf::from(&args[i + 1])) {
+                std::fs::write(root.with_extension("lock"), "4000000000\n").unwrap();
+            }
+            self.0.spawn(cmd)
+        }
+    }
+
+    let sandbox = Sandbox::new("releasing_leaves_locks_taken_over_by_others");
+    let report = sandbox.install(sandbox.options().runner(Usurper(sandbox.cargo.clone())).bin_dir(sandbox.path("bin")).tool(Tool::new("tool"))).unwrap();
+    let lock = report.tools().next().unwrap().cache_dir.with_extension("lock");
+    assert_eq!(std::fs::read_to_string(&lock).unwrap(), "4000000000\n");
+    let leftovers = lock.parent().unwrap().read_dir().unwrap().filter_map(|e| e.ok()).filter(|e| e.file_name().to_string_lossy().contains(".released-")).count();
+    assert_eq!(leftovers, 0);
+}